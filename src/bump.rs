@@ -0,0 +1,130 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::{Cell, UnsafeCell},
+    mem::MaybeUninit,
+    ptr::NonNull,
+};
+
+use crate::Owns;
+
+pub struct BumpAllocator<const SIZE: usize> {
+    used: Cell<usize>,
+    memory: UnsafeCell<[MaybeUninit<u8>; SIZE]>,
+}
+
+impl<const SIZE: usize> BumpAllocator<SIZE> {
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            used: Cell::new(0),
+            memory: UnsafeCell::new([MaybeUninit::uninit(); SIZE]),
+        }
+    }
+
+    #[inline]
+    pub fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    // taking `&mut self` makes sure no box allocated from the arena is alive
+    #[inline]
+    pub fn reset(&mut self) {
+        self.used.set(0);
+    }
+
+    #[inline]
+    fn start(&self) -> usize {
+        self.memory.get() as usize
+    }
+
+    // whether `ptr` is the most recent allocation, which can be resized in place
+    #[inline]
+    fn is_last(&self, ptr: NonNull<u8>, layout: Layout) -> bool {
+        ptr.as_ptr() as usize + layout.size() == self.start() + self.used.get()
+    }
+}
+
+impl<const SIZE: usize> Owns for BumpAllocator<SIZE> {
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let start = self.start();
+        (start..start + SIZE).contains(&(ptr.as_ptr() as usize))
+    }
+}
+
+impl<const SIZE: usize> Default for BumpAllocator<SIZE> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+unsafe impl<const SIZE: usize> Allocator for &BumpAllocator<SIZE> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let start = self.start();
+        let addr = (start + self.used.get())
+            .checked_next_multiple_of(layout.align())
+            .ok_or(AllocError)?;
+
+        let offset = addr - start;
+
+        if offset > SIZE || SIZE - offset < layout.size() {
+            return Err(AllocError);
+        }
+
+        self.used.set(offset + layout.size());
+
+        let ptr = unsafe { (self.memory.get() as *mut u8).add(offset) };
+        Ok(NonNull::slice_from_raw_parts(
+            unsafe { NonNull::new_unchecked(ptr) },
+            layout.size(),
+        ))
+    }
+
+    // memory is only given back by `reset`
+    #[inline]
+    unsafe fn deallocate(&self, _ptr: NonNull<u8>, _layout: Layout) {}
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let offset = ptr.as_ptr() as usize - self.start();
+
+        if self.is_last(ptr, old_layout)
+            && (ptr.as_ptr() as usize).is_multiple_of(new_layout.align())
+            && SIZE - offset >= new_layout.size()
+        {
+            self.used.set(offset + new_layout.size());
+            return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+        }
+
+        let new = self.allocate(new_layout)?;
+        ptr.as_ptr()
+            .copy_to_nonoverlapping(new.as_ptr() as *mut u8, old_layout.size());
+        Ok(new)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !(ptr.as_ptr() as usize).is_multiple_of(new_layout.align()) {
+            let new = self.allocate(new_layout)?;
+            ptr.as_ptr()
+                .copy_to_nonoverlapping(new.as_ptr() as *mut u8, new_layout.size());
+            return Ok(new);
+        }
+
+        if self.is_last(ptr, old_layout) {
+            let offset = ptr.as_ptr() as usize - self.start();
+            self.used.set(offset + new_layout.size());
+        }
+
+        Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()))
+    }
+}
@@ -0,0 +1,231 @@
+use core::{
+    alloc::{Allocator, Layout},
+    mem::forget,
+    ptr::{copy_nonoverlapping, drop_in_place, slice_from_raw_parts_mut, Pointee},
+};
+
+#[cfg(feature = "alloc")]
+use crate::inner::handle_error;
+use crate::{
+    inner::{layout_from_metadata, Inner},
+    SmallBox, SmallBoxError,
+};
+
+// a header followed by a slice, `HeaderSlice<H, [T; N]>` coerces to `HeaderSlice<H, [T]>`
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct HeaderSlice<H, T: ?Sized> {
+    pub header: H,
+    pub slice: T,
+}
+
+/// A dst made of a header and a trailing slice, with the length of that slice as
+/// its metadata.
+///
+/// # Safety
+///
+/// The header and the slice have to be the only fields, since nothing else gets
+/// initialized. Both pointers have to stay within `this`, and the slice has to
+/// keep the length from the metadata of `this`.
+pub unsafe trait SliceDst: Pointee<Metadata = usize> {
+    type Header;
+    type Item;
+
+    /// # Safety
+    ///
+    /// `this` has to point to storage with the layout of `Self` for its
+    /// metadata. It may be uninitialized.
+    unsafe fn header_ptr(this: *mut Self) -> *mut Self::Header;
+
+    /// # Safety
+    ///
+    /// Same as [`SliceDst::header_ptr`].
+    unsafe fn slice_ptr(this: *mut Self) -> *mut [Self::Item];
+}
+
+unsafe impl<H, T> SliceDst for HeaderSlice<H, [T]> {
+    type Header = H;
+    type Item = T;
+
+    #[inline]
+    unsafe fn header_ptr(this: *mut Self) -> *mut H {
+        unsafe { &raw mut (*this).header }
+    }
+
+    #[inline]
+    unsafe fn slice_ptr(this: *mut Self) -> *mut [T] {
+        unsafe { &raw mut (*this).slice }
+    }
+}
+
+// `layout_from_metadata` must not see a length whose size overflows `isize`. the
+// size with an empty slice is at least the offset of the slice, so this errs on
+// the safe side by at most the padding of the header
+#[inline]
+fn check_len<T: ?Sized + SliceDst>(len: usize) -> Result<(), SmallBoxError> {
+    let empty = layout_from_metadata::<T>(0);
+    let slice = Layout::array::<T::Item>(len)?;
+    let size = empty
+        .size()
+        .checked_add(slice.size())
+        .ok_or(SmallBoxError::CapacityOverflow)?;
+
+    Layout::from_size_align(size, empty.align())?;
+    Ok(())
+}
+
+impl<T: ?Sized + SliceDst, S, A: Allocator + Default> SmallBox<T, S, A> {
+    #[inline]
+    pub fn try_from_header_and_slice(
+        header: T::Header,
+        slice: &[T::Item],
+    ) -> Result<Self, SmallBoxError>
+    where
+        T::Item: Copy,
+    {
+        Self::try_from_header_and_slice_in(header, slice, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_header_and_slice(header: T::Header, slice: &[T::Item]) -> Self
+    where
+        T::Item: Copy,
+    {
+        Self::from_header_and_slice_in(header, slice, A::default())
+    }
+
+    #[inline]
+    pub fn try_from_header_and_iter<I>(header: T::Header, iter: I) -> Result<Self, SmallBoxError>
+    where
+        I: IntoIterator<Item = T::Item>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::try_from_header_and_iter_in(header, iter, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_header_and_iter<I>(header: T::Header, iter: I) -> Self
+    where
+        I: IntoIterator<Item = T::Item>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Self::from_header_and_iter_in(header, iter, A::default())
+    }
+}
+
+impl<T: ?Sized + SliceDst, S, A: Allocator> SmallBox<T, S, A> {
+    #[inline]
+    pub fn try_from_header_and_slice_in(
+        header: T::Header,
+        slice: &[T::Item],
+        alloc: A,
+    ) -> Result<Self, SmallBoxError>
+    where
+        T::Item: Copy,
+    {
+        check_len::<T>(slice.len())?;
+
+        let inner = unsafe {
+            Inner::try_new_with_in(slice.len(), alloc, |ptr| {
+                T::header_ptr(ptr).write(header);
+                copy_nonoverlapping(
+                    slice.as_ptr(),
+                    T::slice_ptr(ptr).cast::<T::Item>(),
+                    slice.len(),
+                );
+            })?
+        };
+
+        Ok(Self(inner))
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_header_and_slice_in(header: T::Header, slice: &[T::Item], alloc: A) -> Self
+    where
+        T::Item: Copy,
+    {
+        match Self::try_from_header_and_slice_in(header, slice, alloc) {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    // takes exactly as many items as the iterator reports, and panics if it
+    // runs out before that
+    #[inline]
+    pub fn try_from_header_and_iter_in<I>(
+        header: T::Header,
+        iter: I,
+        alloc: A,
+    ) -> Result<Self, SmallBoxError>
+    where
+        I: IntoIterator<Item = T::Item>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        // drops what is initialized so far if the iterator panics
+        struct Guard<H, T> {
+            header: *mut H,
+            items: *mut T,
+            len: usize,
+        }
+
+        impl<H, T> Drop for Guard<H, T> {
+            #[inline]
+            fn drop(&mut self) {
+                unsafe {
+                    drop_in_place(self.header);
+                    drop_in_place(slice_from_raw_parts_mut(self.items, self.len));
+                }
+            }
+        }
+
+        let mut iter = iter.into_iter();
+        let len = iter.len();
+        check_len::<T>(len)?;
+
+        let inner = unsafe {
+            Inner::try_new_with_in(len, alloc, |ptr| {
+                let mut guard = Guard {
+                    header: T::header_ptr(ptr),
+                    items: T::slice_ptr(ptr).cast::<T::Item>(),
+                    len: 0,
+                };
+
+                guard.header.write(header);
+
+                while guard.len < len {
+                    let item = iter
+                        .next()
+                        .expect("iterator yielded fewer items than its length");
+
+                    guard.items.add(guard.len).write(item);
+                    guard.len += 1;
+                }
+
+                forget(guard);
+            })?
+        };
+
+        Ok(Self(inner))
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_header_and_iter_in<I>(header: T::Header, iter: I, alloc: A) -> Self
+    where
+        I: IntoIterator<Item = T::Item>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        match Self::try_from_header_and_iter_in(header, iter, alloc) {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+}
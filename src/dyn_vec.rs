@@ -0,0 +1,513 @@
+use core::{
+    alloc::{Allocator, Layout},
+    fmt,
+    iter::FusedIterator,
+    marker::{PhantomData, Unsize},
+    mem::{align_of, size_of},
+    ops::{Index, IndexMut},
+    panic::{RefUnwindSafe, UnwindSafe},
+    ptr::{copy, copy_nonoverlapping, drop_in_place, from_raw_parts, from_raw_parts_mut, Pointee},
+    slice,
+};
+
+#[cfg(feature = "alloc")]
+use crate::inner::handle_error;
+use crate::{
+    inner::{coerce_metadata, layout_from_metadata, Data, Heap, Inner},
+    SmallBox, SmallBoxError,
+};
+
+// smallest number of entries a vector allocates room for
+const MIN_ENTRIES_CAPACITY: usize = 4;
+
+// the allocation holds the entry table first, followed by the values packed
+// back to back. value offsets are relative to the start of the value region,
+// which is aligned to the largest alignment of any value pushed so far
+#[cfg(feature = "alloc")]
+pub struct DynVec<T: ?Sized, A: Allocator = alloc::alloc::Global> {
+    phantom: PhantomData<T>,
+    heap: Heap,
+    layout: Layout,
+    entries_cap: usize,
+    data_offset: usize,
+    data_cap: usize,
+    data_len: usize,
+    len: usize,
+    alloc: A,
+}
+
+#[cfg(not(feature = "alloc"))]
+pub struct DynVec<T: ?Sized, A: Allocator> {
+    phantom: PhantomData<T>,
+    heap: Heap,
+    layout: Layout,
+    entries_cap: usize,
+    data_offset: usize,
+    data_cap: usize,
+    data_len: usize,
+    len: usize,
+    alloc: A,
+}
+
+struct Entry<T: ?Sized> {
+    offset: usize,
+    metadata: <T as Pointee>::Metadata,
+}
+
+impl<T: ?Sized> Clone for Entry<T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T: ?Sized> Copy for Entry<T> {}
+
+impl<T: ?Sized, A: Allocator + Default> DynVec<T, A> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+impl<T: ?Sized, A: Allocator> DynVec<T, A> {
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            phantom: PhantomData,
+            heap: Heap::dangling::<Entry<T>>(),
+            layout: Layout::new::<()>(),
+            entries_cap: 0,
+            data_offset: 0,
+            data_cap: 0,
+            data_len: 0,
+            len: 0,
+            alloc,
+        }
+    }
+
+    #[inline]
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn try_push<U: Unsize<T>>(&mut self, value: U) -> Result<(), SmallBoxError> {
+        let offset = self.try_reserve_for(Layout::new::<U>())?;
+
+        unsafe {
+            let entry = Entry {
+                offset,
+                metadata: coerce_metadata::<T, U>(()),
+            };
+
+            (self.data_ptr().add(offset) as *mut U).write(value);
+            self.entries_ptr().add(self.len).write(entry);
+        }
+
+        self.len += 1;
+        self.data_len = offset + size_of::<U>();
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn push<U: Unsize<T>>(&mut self, value: U) {
+        if let Err(err) = self.try_push(value) {
+            handle_error(err)
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        if index < self.len {
+            Some(unsafe { &*self.value_ptr(self.entry(index)) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        if index < self.len {
+            Some(unsafe { &mut *self.value_ptr(self.entry(index)) })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter {
+            entries: unsafe { slice::from_raw_parts(self.entries_ptr(), self.len) }.iter(),
+            data: self.data_ptr(),
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            entries: unsafe { slice::from_raw_parts(self.entries_ptr(), self.len) }.iter(),
+            data: self.data_ptr(),
+            phantom: PhantomData,
+        }
+    }
+
+    #[inline]
+    pub fn try_pop<S>(&mut self) -> Result<Option<SmallBox<T, S, A>>, SmallBoxError>
+    where
+        A: Clone,
+    {
+        if self.len == 0 {
+            Ok(None)
+        } else {
+            self.try_remove(self.len - 1).map(Some)
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn pop<S>(&mut self) -> Option<SmallBox<T, S, A>>
+    where
+        A: Clone,
+    {
+        if self.len == 0 {
+            None
+        } else {
+            Some(self.remove(self.len - 1))
+        }
+    }
+
+    #[inline]
+    pub fn try_remove<S>(&mut self, index: usize) -> Result<SmallBox<T, S, A>, SmallBoxError>
+    where
+        A: Clone,
+    {
+        assert!(index < self.len, "removal index is out of bounds");
+
+        unsafe {
+            let entry = self.entry(index);
+            let layout = layout_from_metadata::<T>(entry.metadata);
+            let mut data = Data::<S>::try_new_uninit_in::<T, _>(entry.metadata, &self.alloc)
+                .map_err(|_| SmallBoxError::AllocError(layout))?;
+
+            copy_nonoverlapping(
+                self.data_ptr().add(entry.offset),
+                data.as_mut_ptr::<T>(entry.metadata) as *mut u8,
+                layout.size(),
+            );
+
+            // close the gap, moving every following value down as far as its
+            // alignment allows
+            let mut end = entry.offset;
+
+            for i in index + 1..self.len {
+                let next = self.entry(i);
+                let layout = layout_from_metadata::<T>(next.metadata);
+                let offset = end.next_multiple_of(layout.align());
+
+                copy(
+                    self.data_ptr().add(next.offset),
+                    self.data_ptr().add(offset),
+                    layout.size(),
+                );
+
+                self.entries_ptr().add(i - 1).write(Entry {
+                    offset,
+                    metadata: next.metadata,
+                });
+
+                end = offset + layout.size();
+            }
+
+            self.len -= 1;
+            self.data_len = end;
+
+            Ok(SmallBox(Inner::from_parts(
+                data,
+                entry.metadata,
+                self.alloc.clone(),
+            )))
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn remove<S>(&mut self, index: usize) -> SmallBox<T, S, A>
+    where
+        A: Clone,
+    {
+        match self.try_remove(index) {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        let len = self.len;
+
+        // if one of the values panics on drop, the rest is leaked
+        self.len = 0;
+        self.data_len = 0;
+
+        for i in 0..len {
+            unsafe { drop_in_place(self.value_ptr(self.entry(i))) };
+        }
+    }
+
+    #[inline]
+    fn entries_ptr(&self) -> *mut Entry<T> {
+        self.heap.as_ptr::<u8>(()) as *mut Entry<T>
+    }
+
+    #[inline]
+    fn data_ptr(&self) -> *mut u8 {
+        unsafe { (self.heap.as_ptr::<u8>(()) as *mut u8).add(self.data_offset) }
+    }
+
+    #[inline]
+    unsafe fn entry(&self, index: usize) -> Entry<T> {
+        self.entries_ptr().add(index).read()
+    }
+
+    #[inline]
+    unsafe fn value_ptr(&self, entry: Entry<T>) -> *mut T {
+        from_raw_parts_mut(self.data_ptr().add(entry.offset), entry.metadata)
+    }
+
+    // makes room for one more entry and a value of the given layout, returning
+    // the offset the value should be written at
+    #[inline]
+    fn try_reserve_for(&mut self, layout: Layout) -> Result<usize, SmallBoxError> {
+        let offset = self.data_len.next_multiple_of(layout.align());
+        let end = offset
+            .checked_add(layout.size())
+            .ok_or(SmallBoxError::CapacityOverflow)?;
+
+        if self.len < self.entries_cap
+            && end <= self.data_cap
+            && layout.align() <= self.layout.align()
+        {
+            Ok(offset)
+        } else {
+            self.try_grow_for(layout, end)?;
+            Ok(offset)
+        }
+    }
+
+    #[cold]
+    fn try_grow_for(&mut self, layout: Layout, end: usize) -> Result<(), SmallBoxError> {
+        let entries_cap = if self.len < self.entries_cap {
+            self.entries_cap
+        } else {
+            (self.len + 1)
+                .max(self.entries_cap.saturating_mul(2))
+                .max(MIN_ENTRIES_CAPACITY)
+        };
+
+        let data_cap = if end <= self.data_cap {
+            self.data_cap
+        } else {
+            end.max(self.data_cap.saturating_mul(2))
+        };
+
+        let align = self
+            .layout
+            .align()
+            .max(layout.align())
+            .max(align_of::<Entry<T>>());
+
+        let data_offset = entries_cap
+            .checked_mul(size_of::<Entry<T>>())
+            .and_then(|size| size.checked_next_multiple_of(align))
+            .ok_or(SmallBoxError::CapacityOverflow)?;
+
+        let size = data_offset
+            .checked_add(data_cap)
+            .ok_or(SmallBoxError::CapacityOverflow)?;
+        let new_layout = Layout::from_size_align(size, align)?;
+        let mut heap = Heap::try_allocate_in(new_layout, &self.alloc)
+            .map_err(|_| SmallBoxError::AllocError(new_layout))?;
+
+        unsafe {
+            copy_nonoverlapping(
+                self.entries_ptr(),
+                heap.as_mut_ptr::<Entry<T>>(()),
+                self.len,
+            );
+            copy_nonoverlapping(
+                self.data_ptr(),
+                heap.as_mut_ptr::<u8>(()).add(data_offset),
+                self.data_len,
+            );
+
+            let old = core::mem::replace(&mut self.heap, heap);
+
+            if self.layout.size() != 0 {
+                old.deallocate_layout(self.layout, &self.alloc);
+            }
+        }
+
+        self.layout = new_layout;
+        self.entries_cap = entries_cap;
+        self.data_offset = data_offset;
+        self.data_cap = data_cap;
+        Ok(())
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Drop for DynVec<T, A> {
+    fn drop(&mut self) {
+        self.clear();
+
+        if self.layout.size() != 0 {
+            let heap = core::mem::replace(&mut self.heap, Heap::dangling::<Entry<T>>());
+            unsafe { heap.deallocate_layout(self.layout, &self.alloc) };
+        }
+    }
+}
+
+impl<T: ?Sized, A: Allocator> Index<usize> for DynVec<T, A> {
+    type Output = T;
+
+    #[inline]
+    fn index(&self, index: usize) -> &T {
+        self.get(index).expect("index is out of bounds")
+    }
+}
+
+impl<T: ?Sized, A: Allocator> IndexMut<usize> for DynVec<T, A> {
+    #[inline]
+    fn index_mut(&mut self, index: usize) -> &mut T {
+        self.get_mut(index).expect("index is out of bounds")
+    }
+}
+
+impl<T: ?Sized, A: Allocator + Default> Default for DynVec<T, A> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a, T: ?Sized, A: Allocator> IntoIterator for &'a DynVec<T, A> {
+    type Item = &'a T;
+    type IntoIter = Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T: ?Sized, A: Allocator> IntoIterator for &'a mut DynVec<T, A> {
+    type Item = &'a mut T;
+    type IntoIter = IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, A: Allocator> fmt::Debug for DynVec<T, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+unsafe impl<T: ?Sized + Send, A: Allocator + Send> Send for DynVec<T, A> {}
+unsafe impl<T: ?Sized + Sync, A: Allocator + Sync> Sync for DynVec<T, A> {}
+
+impl<T: ?Sized, A: Allocator> Unpin for DynVec<T, A> {}
+
+impl<T: ?Sized + UnwindSafe, A: Allocator + UnwindSafe> UnwindSafe for DynVec<T, A> {}
+impl<T: ?Sized + RefUnwindSafe, A: Allocator + RefUnwindSafe> RefUnwindSafe for DynVec<T, A> {}
+
+pub struct Iter<'a, T: ?Sized> {
+    entries: slice::Iter<'a, Entry<T>>,
+    data: *const u8,
+    phantom: PhantomData<&'a T>,
+}
+
+impl<'a, T: ?Sized> Iterator for Iter<'a, T> {
+    type Item = &'a T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a T> {
+        let entry = self.entries.next()?;
+        Some(unsafe { &*from_raw_parts(self.data.add(entry.offset), entry.metadata) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<T: ?Sized> DoubleEndedIterator for Iter<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next_back()?;
+        Some(unsafe { &*from_raw_parts(self.data.add(entry.offset), entry.metadata) })
+    }
+}
+
+impl<T: ?Sized> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T: ?Sized> FusedIterator for Iter<'_, T> {}
+
+unsafe impl<T: ?Sized + Sync> Send for Iter<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for Iter<'_, T> {}
+
+pub struct IterMut<'a, T: ?Sized> {
+    entries: slice::Iter<'a, Entry<T>>,
+    data: *mut u8,
+    phantom: PhantomData<&'a mut T>,
+}
+
+impl<'a, T: ?Sized> Iterator for IterMut<'a, T> {
+    type Item = &'a mut T;
+
+    #[inline]
+    fn next(&mut self) -> Option<&'a mut T> {
+        let entry = self.entries.next()?;
+        Some(unsafe { &mut *from_raw_parts_mut(self.data.add(entry.offset), entry.metadata) })
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+
+impl<T: ?Sized> DoubleEndedIterator for IterMut<'_, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let entry = self.entries.next_back()?;
+        Some(unsafe { &mut *from_raw_parts_mut(self.data.add(entry.offset), entry.metadata) })
+    }
+}
+
+impl<T: ?Sized> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T: ?Sized> FusedIterator for IterMut<'_, T> {}
+
+unsafe impl<T: ?Sized + Send> Send for IterMut<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for IterMut<'_, T> {}
@@ -0,0 +1,40 @@
+use core::{
+    alloc::{AllocError, Layout, LayoutError},
+    error::Error,
+    fmt,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmallBoxError {
+    // the requested size does not fit into `isize`
+    CapacityOverflow,
+    // the allocator failed to allocate a block of this layout
+    AllocError(Layout),
+}
+
+impl fmt::Display for SmallBoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CapacityOverflow => f.write_str("capacity overflow"),
+            Self::AllocError(layout) => {
+                write!(f, "memory allocation of {} bytes failed", layout.size())
+            }
+        }
+    }
+}
+
+impl Error for SmallBoxError {}
+
+impl From<LayoutError> for SmallBoxError {
+    #[inline]
+    fn from(_: LayoutError) -> Self {
+        Self::CapacityOverflow
+    }
+}
+
+impl From<SmallBoxError> for AllocError {
+    #[inline]
+    fn from(_: SmallBoxError) -> Self {
+        AllocError
+    }
+}
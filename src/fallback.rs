@@ -0,0 +1,136 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    ptr::NonNull,
+};
+
+pub trait Owns {
+    fn owns(&self, ptr: NonNull<u8>) -> bool;
+}
+
+impl<T: Owns + ?Sized> Owns for &T {
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        (**self).owns(ptr)
+    }
+}
+
+// a block is freed by whichever allocator owns it, so the primary must keep
+// its blocks valid and keep recognizing them when it is moved, e.g. by being
+// a reference like `&PoolAllocator<N>` rather than holding the memory itself
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Fallback<Primary, Secondary> {
+    primary: Primary,
+    secondary: Secondary,
+}
+
+impl<P, S> Fallback<P, S> {
+    #[inline]
+    pub const fn new(primary: P, secondary: S) -> Self {
+        Self { primary, secondary }
+    }
+
+    #[inline]
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    #[inline]
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+}
+
+impl<P: Owns, S: Owns> Owns for Fallback<P, S> {
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        self.primary.owns(ptr) || self.secondary.owns(ptr)
+    }
+}
+
+unsafe impl<P: Allocator + Owns, S: Allocator> Allocator for Fallback<P, S> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.primary
+            .allocate(layout)
+            .or_else(|_| self.secondary.allocate(layout))
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.primary
+            .allocate_zeroed(layout)
+            .or_else(|_| self.secondary.allocate_zeroed(layout))
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        if self.primary.owns(ptr) {
+            self.primary.deallocate(ptr, layout)
+        } else {
+            self.secondary.deallocate(ptr, layout)
+        }
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.primary.owns(ptr) {
+            return self.secondary.grow(ptr, old_layout, new_layout);
+        }
+
+        match self.primary.grow(ptr, old_layout, new_layout) {
+            Ok(new) => Ok(new),
+
+            // the block outgrew the primary allocator, move it over
+            Err(_) => {
+                let new = self.secondary.allocate(new_layout)?;
+                ptr.as_ptr()
+                    .copy_to_nonoverlapping(new.as_ptr() as *mut u8, old_layout.size());
+                self.primary.deallocate(ptr, old_layout);
+                Ok(new)
+            }
+        }
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !self.primary.owns(ptr) {
+            return self.secondary.grow_zeroed(ptr, old_layout, new_layout);
+        }
+
+        match self.primary.grow_zeroed(ptr, old_layout, new_layout) {
+            Ok(new) => Ok(new),
+
+            Err(_) => {
+                let new = self.secondary.allocate_zeroed(new_layout)?;
+                ptr.as_ptr()
+                    .copy_to_nonoverlapping(new.as_ptr() as *mut u8, old_layout.size());
+                self.primary.deallocate(ptr, old_layout);
+                Ok(new)
+            }
+        }
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if self.primary.owns(ptr) {
+            self.primary.shrink(ptr, old_layout, new_layout)
+        } else {
+            self.secondary.shrink(ptr, old_layout, new_layout)
+        }
+    }
+}
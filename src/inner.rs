@@ -1,19 +1,21 @@
+use crate::SmallBoxError;
 use core::{
     alloc::{AllocError, Allocator, Layout},
     marker::{PhantomData, Unsize},
-    mem::{forget, ManuallyDrop, MaybeUninit},
+    mem::{forget, replace, ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
+    panic::{RefUnwindSafe, UnwindSafe},
     ptr::{
-        copy_nonoverlapping, drop_in_place, from_raw_parts, from_raw_parts_mut, null, read,
-        NonNull, Pointee,
+        copy_nonoverlapping, drop_in_place, from_raw_parts, from_raw_parts_mut, metadata, null,
+        read, slice_from_raw_parts_mut, NonNull, Pointee,
     },
 };
 
-struct Stack<S>(MaybeUninit<S>);
+pub(crate) struct Stack<S>(MaybeUninit<S>);
 
 impl<S> Stack<S> {
     #[inline]
-    fn new_uninit() -> Self {
+    pub(crate) const fn new_uninit() -> Self {
         Self(MaybeUninit::uninit())
     }
 
@@ -22,59 +24,87 @@ impl<S> Stack<S> {
         Self(MaybeUninit::zeroed())
     }
 
+    // copies the bytes of the value behind `src`, which has to fit
     #[inline]
-    unsafe fn from_stack<T: ?Sized, Z>(src: Stack<Z>, metadata: <T as Pointee>::Metadata) -> Self {
-        let layout = layout_from_metadata::<T>(metadata);
+    unsafe fn from_raw<T: ?Sized>(src: *const T) -> Self {
+        let layout = layout_from_metadata::<T>(metadata(src));
         let mut stack = Self::new_uninit();
 
-        copy_nonoverlapping(
-            src.as_ptr::<u8>(()),
-            stack.as_mut_ptr::<u8>(()),
-            layout.size(),
-        );
+        copy_nonoverlapping(src as *const u8, stack.as_mut_ptr::<u8>(()), layout.size());
 
         stack
     }
 
+    #[inline]
+    unsafe fn from_stack<T: ?Sized, Z>(src: Stack<Z>, metadata: <T as Pointee>::Metadata) -> Self {
+        Self::from_raw(src.as_ptr::<T>(metadata))
+    }
+
     #[inline]
     unsafe fn from_heap<T: ?Sized, A: Allocator>(
         heap: Heap,
         metadata: <T as Pointee>::Metadata,
         alloc: &A,
     ) -> Self {
-        let layout = layout_from_metadata::<T>(metadata);
-        let mut stack = Self::new_uninit();
-
-        copy_nonoverlapping(
-            heap.as_ptr::<u8>(()),
-            stack.as_mut_ptr::<u8>(()),
-            layout.size(),
-        );
-
+        let stack = Self::from_raw(heap.as_ptr::<T>(metadata));
         heap.deallocate::<T, _>(metadata, alloc);
-
         stack
     }
 
     #[inline]
-    fn as_ptr<T: ?Sized>(&self, metadata: <T as Pointee>::Metadata) -> *const T {
+    pub(crate) fn as_ptr<T: ?Sized>(&self, metadata: <T as Pointee>::Metadata) -> *const T {
         from_raw_parts(self as *const _ as *const (), metadata)
     }
 
     #[inline]
-    fn as_mut_ptr<T: ?Sized>(&mut self, metadata: <T as Pointee>::Metadata) -> *mut T {
+    pub(crate) fn as_mut_ptr<T: ?Sized>(&mut self, metadata: <T as Pointee>::Metadata) -> *mut T {
         from_raw_parts_mut(self as *mut _ as *mut (), metadata)
     }
+}
+
+pub(crate) struct Heap(NonNull<u8>);
 
+impl Heap {
     #[inline]
-    unsafe fn drop<T: ?Sized>(mut self, metadata: <T as Pointee>::Metadata) {
-        drop_in_place(self.as_mut_ptr::<T>(metadata));
+    pub(crate) const fn dangling<T>() -> Self {
+        Self(NonNull::<T>::dangling().cast())
     }
-}
 
-struct Heap(NonNull<u8>);
+    #[inline]
+    pub(crate) fn try_allocate_in<A: Allocator>(
+        layout: Layout,
+        alloc: &A,
+    ) -> Result<Self, AllocError> {
+        Ok(Self(alloc.allocate(layout)?.cast()))
+    }
+
+    #[inline]
+    pub(crate) unsafe fn try_grow_in<A: Allocator>(
+        &mut self,
+        old_layout: Layout,
+        new_layout: Layout,
+        alloc: &A,
+    ) -> Result<(), AllocError> {
+        self.0 = alloc.grow(self.0, old_layout, new_layout)?.cast();
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) unsafe fn try_shrink_in<A: Allocator>(
+        &mut self,
+        old_layout: Layout,
+        new_layout: Layout,
+        alloc: &A,
+    ) -> Result<(), AllocError> {
+        self.0 = alloc.shrink(self.0, old_layout, new_layout)?.cast();
+        Ok(())
+    }
+
+    #[inline]
+    pub(crate) unsafe fn deallocate_layout<A: Allocator>(self, layout: Layout, alloc: &A) {
+        alloc.deallocate(self.0, layout);
+    }
 
-impl Heap {
     #[inline]
     fn try_new_uninit_in<T: ?Sized, A: Allocator>(
         metadata: <T as Pointee>::Metadata,
@@ -97,25 +127,29 @@ impl Heap {
         ))
     }
 
+    // copies the bytes of the value behind `src` into a new block
+    #[inline]
+    unsafe fn try_from_raw_in<T: ?Sized, A: Allocator>(
+        src: *const T,
+        alloc: &A,
+    ) -> Result<Self, AllocError> {
+        let metadata = metadata(src);
+        let mut heap = Self::try_new_uninit_in::<T, _>(metadata, alloc)?;
+        let layout = layout_from_metadata::<T>(metadata);
+
+        copy_nonoverlapping(src as *const u8, heap.as_mut_ptr::<u8>(()), layout.size());
+
+        Ok(heap)
+    }
+
     #[inline]
     unsafe fn try_from_stack_in<T: ?Sized, S, A: Allocator>(
         stack: Stack<S>,
         metadata: <T as Pointee>::Metadata,
         alloc: &A,
     ) -> Result<Self, Stack<S>> {
-        match Self::try_new_uninit_in::<T, _>(metadata, alloc) {
-            Ok(mut heap) => {
-                let layout = layout_from_metadata::<T>(metadata);
-
-                copy_nonoverlapping(
-                    stack.as_ptr::<u8>(()),
-                    heap.as_mut_ptr::<u8>(()),
-                    layout.size(),
-                );
-
-                Ok(heap)
-            }
-
+        match Self::try_from_raw_in(stack.as_ptr::<T>(metadata), alloc) {
+            Ok(heap) => Ok(heap),
             Err(_) => Err(stack),
         }
     }
@@ -127,12 +161,12 @@ impl Heap {
     }
 
     #[inline]
-    fn as_ptr<T: ?Sized>(&self, metadata: <T as Pointee>::Metadata) -> *const T {
+    pub(crate) fn as_ptr<T: ?Sized>(&self, metadata: <T as Pointee>::Metadata) -> *const T {
         from_raw_parts(self.0.as_ptr() as *const (), metadata)
     }
 
     #[inline]
-    fn as_mut_ptr<T: ?Sized>(&mut self, metadata: <T as Pointee>::Metadata) -> *mut T {
+    pub(crate) fn as_mut_ptr<T: ?Sized>(&mut self, metadata: <T as Pointee>::Metadata) -> *mut T {
         from_raw_parts_mut(self.0.as_ptr() as *mut (), metadata)
     }
 
@@ -156,9 +190,9 @@ impl Heap {
     }
 }
 
-union Data<S> {
-    stack: ManuallyDrop<Stack<S>>,
-    heap: ManuallyDrop<Heap>,
+pub(crate) union Data<S> {
+    pub(crate) stack: ManuallyDrop<Stack<S>>,
+    pub(crate) heap: ManuallyDrop<Heap>,
 }
 
 impl<S> Data<S> {
@@ -194,6 +228,39 @@ impl<S> Data<S> {
         }
     }
 
+    // copies the bytes of the value behind `src` into new storage
+    #[inline]
+    unsafe fn try_from_raw_in<T: ?Sized, A: Allocator>(
+        src: *const T,
+        alloc: &A,
+    ) -> Result<Self, AllocError> {
+        if Self::inlined::<T>(metadata(src)) {
+            Ok(Self {
+                stack: ManuallyDrop::new(Stack::from_raw(src)),
+            })
+        } else {
+            Ok(Self {
+                heap: ManuallyDrop::new(Heap::try_from_raw_in(src, alloc)?),
+            })
+        }
+    }
+
+    // moves the bytes of the value out to `dst` and frees the storage
+    #[inline]
+    unsafe fn move_to<T: ?Sized, A: Allocator>(
+        self,
+        dst: *mut u8,
+        metadata: <T as Pointee>::Metadata,
+        alloc: &A,
+    ) {
+        let layout = layout_from_metadata::<T>(metadata);
+        copy_nonoverlapping(self.as_ptr::<T>(metadata) as *const u8, dst, layout.size());
+
+        if !Self::inlined::<T>(metadata) {
+            ManuallyDrop::into_inner(self.heap).deallocate::<T, _>(metadata, alloc);
+        }
+    }
+
     // #[inline]
     // unsafe fn try_from_stack_in<T: ?Sized, A: Allocator>(stack: Stack<S>, metadata: <T as Pointee>::Metadata, alloc: &A) -> Result<Self, Stack<S>> {
     //
@@ -240,7 +307,7 @@ impl<S> Data<S> {
     }
 
     #[inline]
-    fn try_new_uninit_in<T: ?Sized, A: Allocator>(
+    pub(crate) fn try_new_uninit_in<T: ?Sized, A: Allocator>(
         metadata: <T as Pointee>::Metadata,
         alloc: &A,
     ) -> Result<Self, AllocError> {
@@ -272,7 +339,7 @@ impl<S> Data<S> {
     }
 
     #[inline]
-    fn as_ptr<T: ?Sized>(&self, metadata: <T as Pointee>::Metadata) -> *const T {
+    pub(crate) fn as_ptr<T: ?Sized>(&self, metadata: <T as Pointee>::Metadata) -> *const T {
         unsafe {
             if Self::inlined::<T>(metadata) {
                 self.stack.as_ptr(metadata)
@@ -283,7 +350,7 @@ impl<S> Data<S> {
     }
 
     #[inline]
-    fn as_mut_ptr<T: ?Sized>(&mut self, metadata: <T as Pointee>::Metadata) -> *mut T {
+    pub(crate) fn as_mut_ptr<T: ?Sized>(&mut self, metadata: <T as Pointee>::Metadata) -> *mut T {
         unsafe {
             if Self::inlined::<T>(metadata) {
                 self.stack.as_mut_ptr(metadata)
@@ -294,22 +361,7 @@ impl<S> Data<S> {
     }
 
     #[inline]
-    unsafe fn drop<T: ?Sized, A: Allocator>(
-        &mut self,
-        metadata: <T as Pointee>::Metadata,
-        alloc: &A,
-    ) {
-        unsafe {
-            if Self::inlined::<T>(metadata) {
-                ManuallyDrop::take(&mut self.stack).drop::<T>(metadata)
-            } else {
-                ManuallyDrop::take(&mut self.heap).drop::<T, _>(metadata, alloc)
-            }
-        }
-    }
-
-    #[inline]
-    const fn inlined<T: ?Sized>(metadata: <T as Pointee>::Metadata) -> bool {
+    pub(crate) const fn inlined<T: ?Sized>(metadata: <T as Pointee>::Metadata) -> bool {
         let store = Layout::new::<S>();
         let layout = layout_from_metadata::<T>(metadata);
 
@@ -317,6 +369,9 @@ impl<S> Data<S> {
     }
 }
 
+// the metadata is stored as is, so a sized box takes no more than its storage.
+// this makes `Inner` invariant in `T`, as the metadata is a projection of it, and
+// a field that is covariant instead would be a pointer, one word more
 pub struct Inner<T: ?Sized, S, A: Allocator> {
     phantom: PhantomData<T>,
     metadata: <T as Pointee>::Metadata,
@@ -326,69 +381,273 @@ pub struct Inner<T: ?Sized, S, A: Allocator> {
 
 impl<T: Sized, S, A: Allocator> Inner<MaybeUninit<T>, S, A> {
     #[inline]
-    pub fn try_new_uninit_in(alloc: A) -> Result<Self, AllocError> {
-        Ok(Self {
-            phantom: PhantomData,
-            metadata: (),
-            data: Data::try_new_uninit_in::<T, _>((), &alloc)?,
-            alloc,
-        })
+    pub fn try_new_uninit_in(alloc: A) -> Result<Self, SmallBoxError> {
+        let data = Data::try_new_uninit_in::<T, _>((), &alloc)
+            .map_err(|_| SmallBoxError::AllocError(Layout::new::<T>()))?;
+
+        Ok(unsafe { Self::from_parts(data, (), alloc) })
     }
 
     #[inline]
-    pub fn try_new_zeroed_in(alloc: A) -> Result<Self, AllocError> {
-        Ok(Self {
-            phantom: PhantomData,
-            metadata: (),
-            data: Data::try_new_zeroed_in::<T, _>((), &alloc)?,
-            alloc,
-        })
+    pub fn try_new_zeroed_in(alloc: A) -> Result<Self, SmallBoxError> {
+        let data = Data::try_new_zeroed_in::<T, _>((), &alloc)
+            .map_err(|_| SmallBoxError::AllocError(Layout::new::<T>()))?;
+
+        Ok(unsafe { Self::from_parts(data, (), alloc) })
     }
 
     #[inline]
     pub unsafe fn assume_init(self) -> Inner<T, S, A> {
-        let (data, metadata, alloc) = self.into_parts();
-
-        Inner {
-            phantom: PhantomData,
-            metadata,
-            data,
-            alloc,
-        }
+        self.cast(())
     }
 }
 
 impl<T: Sized, S, A: Allocator> Inner<[MaybeUninit<T>], S, A> {
     #[inline]
-    pub fn try_new_uninit_slice_in(len: usize, alloc: A) -> Result<Self, AllocError> {
-        Ok(Self {
-            phantom: PhantomData,
-            metadata: len,
-            data: Data::try_new_uninit_in::<[T], _>(len, &alloc)?,
-            alloc,
-        })
+    pub fn try_new_uninit_slice_in(len: usize, alloc: A) -> Result<Self, SmallBoxError> {
+        // `layout_from_metadata` must not see a length whose size overflows `isize`
+        let layout = Layout::array::<T>(len)?;
+        let data = Data::try_new_uninit_in::<[T], _>(len, &alloc)
+            .map_err(|_| SmallBoxError::AllocError(layout))?;
+
+        Ok(unsafe { Self::from_parts(data, len, alloc) })
     }
 
     #[inline]
-    pub fn try_new_zeroed_slice_in(len: usize, alloc: A) -> Result<Self, AllocError> {
-        Ok(Self {
-            phantom: PhantomData,
-            metadata: len,
-            data: Data::try_new_zeroed_in::<[T], _>(len, &alloc)?,
-            alloc,
-        })
+    pub fn try_new_zeroed_slice_in(len: usize, alloc: A) -> Result<Self, SmallBoxError> {
+        let layout = Layout::array::<T>(len)?;
+        let data = Data::try_new_zeroed_in::<[T], _>(len, &alloc)
+            .map_err(|_| SmallBoxError::AllocError(layout))?;
+
+        Ok(unsafe { Self::from_parts(data, len, alloc) })
     }
 
     #[inline]
     pub unsafe fn assume_init(self) -> Inner<[T], S, A> {
-        let (data, metadata, alloc) = self.into_parts();
+        let len = self.metadata();
+        self.cast(len)
+    }
+}
 
-        Inner {
-            phantom: PhantomData,
-            metadata,
-            data,
-            alloc,
+impl<T: Sized, S, A: Allocator> Inner<[T], S, A> {
+    #[inline]
+    pub fn try_truncate(&mut self, len: usize) -> Result<(), SmallBoxError> {
+        let old_len = self.metadata();
+
+        if len >= old_len {
+            return Ok(());
+        }
+
+        unsafe {
+            // the new length is committed before the tail is dropped in place, so a
+            // panicking destructor can only leak the rest of it. the guard then fits
+            // the storage to the new length
+            struct Guard<'a, T, S, A: Allocator> {
+                inner: &'a mut Inner<[T], S, A>,
+                old_len: usize,
+            }
+
+            impl<T, S, A: Allocator> Drop for Guard<'_, T, S, A> {
+                fn drop(&mut self) {
+                    // the tail is gone, so there is no state left to return to
+                    let abort = Abort;
+                    let len = self.inner.metadata();
+
+                    if unsafe { self.inner.try_move_storage(self.old_len, len) }.is_err() {
+                        panic!("failed to shrink the storage of a SmallBox");
+                    }
+
+                    forget(abort);
+                }
+            }
+
+            let tail = self.elements(old_len).add(len);
+            self.set_metadata(len);
+
+            let guard = Guard {
+                inner: self,
+                old_len,
+            };
+
+            drop_in_place(slice_from_raw_parts_mut(tail, old_len - len));
+            drop(guard);
         }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn try_grow_with<F: FnMut() -> T>(
+        &mut self,
+        len: usize,
+        mut fill: F,
+    ) -> Result<(), SmallBoxError> {
+        let old_len = self.metadata();
+
+        if len <= old_len {
+            return Ok(());
+        }
+
+        let layout = Layout::array::<T>(len)?;
+
+        unsafe {
+            self.try_move_storage(old_len, len)
+                .map_err(|_| SmallBoxError::AllocError(layout))?;
+
+            // on panic, drop what was written so far and move the storage back
+            struct Guard<'a, T, S, A: Allocator> {
+                inner: &'a mut Inner<[T], S, A>,
+                old_len: usize,
+                len: usize,
+                written: usize,
+            }
+
+            impl<T, S, A: Allocator> Drop for Guard<'_, T, S, A> {
+                fn drop(&mut self) {
+                    unsafe {
+                        let ptr = self.inner.elements(self.len);
+                        drop_in_place(slice_from_raw_parts_mut(
+                            ptr.add(self.old_len),
+                            self.written,
+                        ));
+
+                        if self.inner.try_move_storage(self.len, self.old_len).is_err() {
+                            // this runs during unwinding, so this aborts
+                            panic!("failed to restore the storage of a SmallBox");
+                        }
+                    }
+                }
+            }
+
+            let mut guard = Guard {
+                inner: self,
+                old_len,
+                len,
+                written: 0,
+            };
+
+            let ptr = guard.inner.elements(len);
+
+            while old_len + guard.written < len {
+                ptr.add(old_len + guard.written).write(fill());
+                guard.written += 1;
+            }
+
+            forget(guard);
+        }
+
+        self.set_metadata(len);
+        Ok(())
+    }
+
+    // clones `src` into the box, moving the storage to the new length instead of
+    // replacing it. on error the length is kept, with the shared prefix already
+    // cloned from `src`
+    #[inline]
+    pub fn try_clone_from_slice(&mut self, src: &[T]) -> Result<(), SmallBoxError>
+    where
+        T: Clone,
+    {
+        let old_len = self.metadata();
+        let len = src.len();
+        let (head, tail) = src.split_at(old_len.min(len));
+
+        unsafe {
+            (*slice_from_raw_parts_mut(self.elements(old_len), head.len())).clone_from_slice(head);
+        }
+
+        if len >= old_len {
+            let mut tail = tail.iter();
+            return self.try_grow_with(len, || tail.next().unwrap().clone());
+        }
+
+        self.try_truncate(len)
+    }
+
+    // the elements of the storage, assuming it was sized for `len` elements
+    #[inline]
+    fn elements(&mut self, len: usize) -> *mut T {
+        self.data.as_mut_ptr::<[T]>(len) as *mut T
+    }
+
+    // moves the storage from fitting `old_len` elements to fitting `len` elements,
+    // keeping the bytes of the first `min(old_len, len)` elements. the metadata is
+    // left untouched, on error the storage is not changed
+    #[inline]
+    unsafe fn try_move_storage(&mut self, old_len: usize, len: usize) -> Result<(), AllocError> {
+        let old_layout = layout_from_metadata::<[T]>(old_len);
+        let layout = layout_from_metadata::<[T]>(len);
+        let keep = old_len.min(len);
+
+        match (Self::inlined(old_len), Self::inlined(len)) {
+            (true, true) => {}
+
+            (true, false) => {
+                let mut heap = Heap::try_allocate_in(layout, &self.alloc)?;
+                copy_nonoverlapping(
+                    self.data.stack.as_ptr::<T>(()),
+                    heap.as_mut_ptr::<T>(()),
+                    keep,
+                );
+                self.data = Data {
+                    heap: ManuallyDrop::new(heap),
+                };
+            }
+
+            (false, true) => {
+                let heap = ManuallyDrop::take(&mut self.data.heap);
+                let mut stack = Stack::new_uninit();
+                copy_nonoverlapping(heap.as_ptr::<T>(()), stack.as_mut_ptr::<T>(()), keep);
+                self.data = Data {
+                    stack: ManuallyDrop::new(stack),
+                };
+                heap.deallocate_layout(old_layout, &self.alloc);
+            }
+
+            (false, false) => {
+                if layout.size() > old_layout.size() {
+                    (*self.data.heap).try_grow_in(old_layout, layout, &self.alloc)?;
+                } else if (*self.data.heap)
+                    .try_shrink_in(old_layout, layout, &self.alloc)
+                    .is_err()
+                {
+                    // the allocator refused to shrink the block, so the elements move
+                    // to a smaller one instead
+                    let mut heap = Heap::try_allocate_in(layout, &self.alloc)?;
+                    copy_nonoverlapping(
+                        self.data.heap.as_ptr::<T>(()),
+                        heap.as_mut_ptr::<T>(()),
+                        keep,
+                    );
+                    replace(&mut *self.data.heap, heap).deallocate_layout(old_layout, &self.alloc);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+struct DeallocGuard<'a, A: Allocator> {
+    heap: ManuallyDrop<Heap>,
+    layout: Layout,
+    alloc: &'a A,
+}
+
+impl<A: Allocator> Drop for DeallocGuard<'_, A> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe { ManuallyDrop::take(&mut self.heap).deallocate_layout(self.layout, self.alloc) }
+    }
+}
+
+// panics when dropped, which aborts when that happens during unwinding
+struct Abort;
+
+impl Drop for Abort {
+    #[inline]
+    fn drop(&mut self) {
+        panic!("failed to shrink the storage of a SmallBox");
     }
 }
 
@@ -400,7 +659,7 @@ impl<T: ?Sized, S, A: Allocator> Inner<T, S, A> {
 
     #[inline]
     pub const fn is_inlined(&self) -> bool {
-        Self::inlined(self.metadata)
+        Self::inlined(self.metadata())
     }
 
     #[inline]
@@ -409,38 +668,131 @@ impl<T: ?Sized, S, A: Allocator> Inner<T, S, A> {
     }
 
     #[inline]
-    #[cfg(feature = "alloc")]
-    pub fn metadata(&self) -> <T as Pointee>::Metadata {
+    pub const fn metadata(&self) -> <T as Pointee>::Metadata {
         self.metadata
     }
 
     #[inline]
-    fn into_parts(self) -> (Data<S>, <T as Pointee>::Metadata, A) {
-        unsafe {
-            let metadata = self.metadata;
-            let data = read(&self.data as *const _);
-            let alloc = read(&self.alloc as *const _);
+    fn set_metadata(&mut self, metadata: <T as Pointee>::Metadata) {
+        self.metadata = metadata;
+    }
 
-            forget(self);
-            (data, metadata, alloc)
+    #[inline]
+    pub(crate) unsafe fn from_parts(
+        data: Data<S>,
+        metadata: <T as Pointee>::Metadata,
+        alloc: A,
+    ) -> Self {
+        Self {
+            phantom: PhantomData,
+            metadata,
+            data,
+            alloc,
         }
     }
 
     #[inline]
-    pub fn coerce<U: ?Sized>(self) -> Inner<U, S, A>
-    where
-        T: Unsize<U>,
-    {
-        let (data, metadata, alloc) = self.into_parts();
+    pub(crate) fn into_parts(self) -> (Data<S>, <T as Pointee>::Metadata, A) {
+        let this = ManuallyDrop::new(self);
+        unsafe { (read(&this.data), this.metadata, read(&this.alloc)) }
+    }
+
+    // the value must stay in place, so `U` has to have the layout of `T`
+    #[inline]
+    const unsafe fn cast<U: ?Sized>(self, metadata: <U as Pointee>::Metadata) -> Inner<U, S, A> {
+        // `ManuallyDrop` can not be dereferenced in a const fn
+        let this = ManuallyDrop::new(self);
+        let this = &this as *const ManuallyDrop<Self> as *const Self;
 
         Inner {
             phantom: PhantomData,
-            metadata: coerce_metadata::<U, T>(metadata),
-            data,
+            metadata,
+            data: read(&(*this).data),
+            alloc: read(&(*this).alloc),
+        }
+    }
+
+    // copies the bytes of `src` into the inline space. the caller makes sure they
+    // fit and that the copy may take ownership of them
+    #[inline]
+    pub const unsafe fn copy_inline_unchecked(src: *const T, alloc: A) -> Self {
+        let metadata = metadata(src);
+        let mut stack = Stack::new_uninit();
+
+        copy_nonoverlapping(
+            src as *const u8,
+            stack.0.as_mut_ptr() as *mut u8,
+            layout_from_metadata::<T>(metadata).size(),
+        );
+
+        Self {
+            phantom: PhantomData,
+            metadata,
+            data: Data {
+                stack: ManuallyDrop::new(stack),
+            },
             alloc,
         }
     }
 
+    #[inline]
+    pub const fn coerce<U: ?Sized>(self) -> Inner<U, S, A>
+    where
+        T: Unsize<U>,
+    {
+        let metadata = coerce_metadata::<U, T>(self.metadata());
+        unsafe { self.cast(metadata) }
+    }
+
+    // `init` gets uninitialized storage for the value and has to fill it in
+    // completely. if it unwinds, it has to leave nothing behind that needs a drop
+    #[inline]
+    pub(crate) unsafe fn try_new_with_in<F: FnOnce(*mut T)>(
+        metadata: <T as Pointee>::Metadata,
+        alloc: A,
+        init: F,
+    ) -> Result<Self, SmallBoxError> {
+        let layout = layout_from_metadata::<T>(metadata);
+        let mut data = Data::try_new_uninit_in::<T, _>(metadata, &alloc)
+            .map_err(|_| SmallBoxError::AllocError(layout))?;
+
+        if Self::inlined(metadata) {
+            init(data.as_mut_ptr::<T>(metadata));
+        } else {
+            let guard = DeallocGuard {
+                heap: ManuallyDrop::new(Heap(data.heap.0)),
+                layout,
+                alloc: &alloc,
+            };
+
+            init(data.as_mut_ptr::<T>(metadata));
+            forget(guard);
+        }
+
+        Ok(Self::from_parts(data, metadata, alloc))
+    }
+
+    // on success the value behind `src` belongs to the box and must not be dropped
+    #[inline]
+    pub unsafe fn try_from_unsized_move_in(src: *mut T, alloc: A) -> Result<Self, SmallBoxError> {
+        let metadata = metadata(src);
+
+        match Data::try_from_raw_in(src, &alloc) {
+            Ok(data) => Ok(Self::from_parts(data, metadata, alloc)),
+            Err(_) => Err(SmallBoxError::AllocError(layout_from_metadata::<T>(
+                metadata,
+            ))),
+        }
+    }
+
+    // `dst` has to be valid for writes of the layout of the value
+    #[inline]
+    pub unsafe fn into_unsized_move(self, dst: *mut u8) -> *mut T {
+        let (data, metadata, alloc) = self.into_parts();
+        data.move_to::<T, _>(dst, metadata, &alloc);
+        from_raw_parts_mut(dst, metadata)
+    }
+
     #[inline]
     #[cfg(feature = "alloc")]
     pub fn from_box(boxed: alloc::boxed::Box<T, A>) -> Self {
@@ -448,11 +800,9 @@ impl<T: ?Sized, S, A: Allocator> Inner<T, S, A> {
         let (src, metadata) = src.to_raw_parts();
         let heap = unsafe { Heap::from_raw(src as *mut u8) };
 
-        Self {
-            phantom: PhantomData,
-            data: unsafe { Data::from_heap::<T, _>(heap, metadata, &alloc) },
-            metadata,
-            alloc,
+        unsafe {
+            let data = Data::from_heap::<T, _>(heap, metadata, &alloc);
+            Self::from_parts(data, metadata, alloc)
         }
     }
 
@@ -467,12 +817,7 @@ impl<T: ?Sized, S, A: Allocator> Inner<T, S, A> {
                     heap.as_mut_ptr(metadata),
                     alloc,
                 )),
-                Err(data) => Err(Self {
-                    phantom: PhantomData,
-                    metadata,
-                    data,
-                    alloc,
-                }),
+                Err(data) => Err(Self::from_parts(data, metadata, alloc)),
             }
         }
     }
@@ -483,19 +828,8 @@ impl<T: ?Sized, S, A: Allocator> Inner<T, S, A> {
 
         unsafe {
             match Data::<Z>::try_from_data_in::<T, _, _>(data, metadata, &alloc) {
-                Ok(data) => Ok(Inner {
-                    phantom: PhantomData,
-                    metadata,
-                    data,
-                    alloc,
-                }),
-
-                Err(data) => Err(Self {
-                    phantom: PhantomData,
-                    metadata,
-                    data,
-                    alloc,
-                }),
+                Ok(data) => Ok(Inner::from_parts(data, metadata, alloc)),
+                Err(data) => Err(Self::from_parts(data, metadata, alloc)),
             }
         }
     }
@@ -506,33 +840,40 @@ impl<T: ?Sized, S, A: Allocator> Inner<T, S, A> {
     >(
         self,
     ) -> Inner<U, S, A> {
-        let (data, metadata, alloc) = self.into_parts();
-
-        Inner {
-            phantom: PhantomData,
-            metadata,
-            data,
-            alloc,
-        }
+        let metadata = self.metadata();
+        self.cast(metadata)
     }
 
     #[inline]
     pub unsafe fn downcast_unchecked<U: Sized>(self) -> Inner<U, S, A> {
-        let (data, _, alloc) = self.into_parts();
+        self.cast(())
+    }
 
-        Inner {
-            phantom: PhantomData,
-            metadata: (),
-            data,
-            alloc,
-        }
+    #[inline]
+    fn as_ptr(&self) -> *const T {
+        self.data.as_ptr(self.metadata)
+    }
+
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr(self.metadata)
     }
 }
 
-impl<T: ?Sized, S, A: Allocator> Drop for Inner<T, S, A> {
+// same as `Box`, dropping never touches a `T` other than the owned one, which
+// `PhantomData<T>` tells dropck about
+unsafe impl<#[may_dangle] T: ?Sized, S, A: Allocator> Drop for Inner<T, S, A> {
     #[inline]
     fn drop(&mut self) {
-        unsafe { self.data.drop::<T, _>(self.metadata, &self.alloc) }
+        let metadata = self.metadata;
+
+        unsafe {
+            if Self::inlined(metadata) {
+                drop_in_place(self.data.as_mut_ptr::<T>(metadata))
+            } else {
+                ManuallyDrop::take(&mut self.data.heap).drop::<T, _>(metadata, &self.alloc)
+            }
+        }
     }
 }
 
@@ -541,33 +882,40 @@ impl<T: ?Sized, S, A: Allocator> Deref for Inner<T, S, A> {
 
     #[inline]
     fn deref(&self) -> &Self::Target {
-        unsafe { &*self.data.as_ptr::<T>(self.metadata) }
+        unsafe { &*self.as_ptr() }
     }
 }
 
 impl<T: ?Sized, S, A: Allocator> DerefMut for Inner<T, S, A> {
     #[inline]
     fn deref_mut(&mut self) -> &mut Self::Target {
-        unsafe { &mut *self.data.as_mut_ptr::<T>(self.metadata) }
+        unsafe { &mut *self.as_mut_ptr() }
     }
 }
 
-unsafe impl<T: ?Sized + Send, S, A: Allocator> Send for Inner<T, S, A> {}
-unsafe impl<T: ?Sized + Sync, S, A: Allocator> Sync for Inner<T, S, A> {}
+// `S` only describes the inline space, it never holds an actual `S`,
+// so none of the auto traits below depend on it
+unsafe impl<T: ?Sized + Send, S, A: Allocator + Send> Send for Inner<T, S, A> {}
+unsafe impl<T: ?Sized + Sync, S, A: Allocator + Sync> Sync for Inner<T, S, A> {}
+
+// `Unpin` regardless of `T`, like `Box`: pinning the box never pins what it
+// holds. unlike with `Box`, inline contents do move along with the box, which
+// is also why there is no safe way to pin them, like `Box::pin`
+impl<T: ?Sized, S, A: Allocator> Unpin for Inner<T, S, A> {}
+
+impl<T: ?Sized + UnwindSafe, S, A: Allocator + UnwindSafe> UnwindSafe for Inner<T, S, A> {}
+impl<T: ?Sized + RefUnwindSafe, S, A: Allocator + RefUnwindSafe> RefUnwindSafe for Inner<T, S, A> {}
 
 #[inline(always)]
-const fn layout_from_metadata<T: ?Sized>(metadata: <T as Pointee>::Metadata) -> Layout {
-    unsafe { Layout::for_value_raw(from_raw_parts::<T>(null(), metadata)) }
+pub(crate) const fn layout_from_metadata<T: ?Sized>(metadata: <T as Pointee>::Metadata) -> Layout {
+    unsafe { Layout::for_value_raw(from_raw_parts::<T>(null::<()>(), metadata)) }
 }
 
 #[inline(always)]
-const fn coerce_metadata<U: ?Sized, T: ?Sized>(
+pub(crate) const fn coerce_metadata<U: ?Sized, T: ?Sized + Unsize<U>>(
     metadata: <T as Pointee>::Metadata,
-) -> <U as Pointee>::Metadata
-where
-    T: Unsize<U>,
-{
-    let ptr = from_raw_parts::<T>(null(), metadata) as *const U;
+) -> <U as Pointee>::Metadata {
+    let ptr = from_raw_parts::<T>(null::<()>(), metadata) as *const U;
     let (_, metadata) = ptr.to_raw_parts();
     metadata
 }
@@ -579,3 +927,14 @@ where
 pub fn handle_alloc_error<T: ?Sized>(metadata: <T as Pointee>::Metadata) -> ! {
     alloc::alloc::handle_alloc_error(layout_from_metadata::<T>(metadata))
 }
+
+#[cold]
+#[inline(never)]
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+pub fn handle_error(err: SmallBoxError) -> ! {
+    match err {
+        SmallBoxError::CapacityOverflow => panic!("capacity overflow"),
+        SmallBoxError::AllocError(layout) => alloc::alloc::handle_alloc_error(layout),
+    }
+}
@@ -1,38 +1,68 @@
 #![no_std]
-#![feature(
-    ptr_metadata,
-    layout_for_ptr,
-    allocator_api,
-    const_alloc_layout,
-    unsize,
-    maybe_uninit_write_slice
-)]
+#![feature(ptr_metadata, layout_for_ptr, allocator_api, unsize, dropck_eyepatch)]
 // #![warn(missing_docs)]
-//#![deny(clippy::missing_safety_doc)]
+#![deny(clippy::missing_safety_doc)]
 #![doc = include_str!("../README.md")]
 
 #[cfg(feature = "alloc")]
 extern crate alloc;
 
+#[cfg(feature = "std")]
+extern crate std;
+
+mod error;
+
 mod inner;
 
+pub mod vec;
+
+pub mod dyn_vec;
+
+pub mod thin;
+
+pub mod dst;
+
+mod pool;
+
+mod bump;
+
+mod fallback;
+
+#[cfg(feature = "testing")]
+pub mod testing;
+
+mod string;
+
+#[cfg(feature = "serde")]
+mod serde;
+
 #[cfg(feature = "alloc")]
-use inner::handle_alloc_error;
+use inner::{handle_alloc_error, handle_error};
 
 use core::{
-    alloc::{AllocError, Allocator},
-    any::{Any, TypeId},
+    alloc::Allocator,
+    any::Any,
     borrow::{Borrow, BorrowMut},
     cmp::Ordering,
     fmt,
     hash::{Hash, Hasher},
     marker::Unsize,
-    mem::MaybeUninit,
+    mem::{forget, ManuallyDrop, MaybeUninit},
     ops::{Deref, DerefMut},
-    ptr::read,
+    ptr::{drop_in_place, read, slice_from_raw_parts_mut},
 };
 use inner::Inner;
 
+pub use bump::BumpAllocator;
+pub use dst::{HeaderSlice, SliceDst};
+pub use dyn_vec::DynVec;
+pub use error::SmallBoxError;
+pub use fallback::{Fallback, Owns};
+pub use pool::PoolAllocator;
+pub use string::SmallString;
+pub use thin::ThinSmallBox;
+pub use vec::SmallBoxVec;
+
 #[cfg(feature = "alloc")]
 pub struct SmallBox<T: ?Sized, Space, A: Allocator = alloc::alloc::Global>(Inner<T, Space, A>);
 
@@ -41,7 +71,7 @@ pub struct SmallBox<T: ?Sized, Space, A: Allocator>(Inner<T, Space, A>);
 
 impl<T: Sized, S, A: Allocator + Default> SmallBox<T, S, A> {
     #[inline]
-    pub fn try_new(value: T) -> Result<Self, AllocError> {
+    pub fn try_new(value: T) -> Result<Self, SmallBoxError> {
         Self::try_new_in(value, A::default())
     }
 
@@ -53,9 +83,34 @@ impl<T: Sized, S, A: Allocator + Default> SmallBox<T, S, A> {
     }
 }
 
+#[cfg(feature = "alloc")]
+impl<T: Sized, S> SmallBox<T, S> {
+    // `A::default()` can not be called in a const fn, so these are for `Global` only
+    #[inline]
+    pub const fn new_inline(value: T) -> Self {
+        Self::new_inline_in(value, alloc::alloc::Global)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<T: Sized, S> SmallBox<[T], S> {
+    #[inline]
+    pub const fn from_array_inline<const N: usize>(array: [T; N]) -> Self {
+        Self::from_array_inline_in(array, alloc::alloc::Global)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> SmallBox<str, S> {
+    #[inline]
+    pub const fn from_str_inline(value: &str) -> Self {
+        Self::from_str_inline_in(value, alloc::alloc::Global)
+    }
+}
+
 impl<T: Sized, S, A: Allocator + Default> SmallBox<MaybeUninit<T>, S, A> {
     #[inline]
-    pub fn try_new_uninit() -> Result<Self, AllocError> {
+    pub fn try_new_uninit() -> Result<Self, SmallBoxError> {
         Self::try_new_uninit_in(A::default())
     }
 
@@ -67,7 +122,7 @@ impl<T: Sized, S, A: Allocator + Default> SmallBox<MaybeUninit<T>, S, A> {
     }
 
     #[inline]
-    pub fn try_new_zeroed() -> Result<Self, AllocError> {
+    pub fn try_new_zeroed() -> Result<Self, SmallBoxError> {
         Self::try_new_zeroed_in(A::default())
     }
 
@@ -81,7 +136,7 @@ impl<T: Sized, S, A: Allocator + Default> SmallBox<MaybeUninit<T>, S, A> {
 
 impl<T: Sized, S, A: Allocator> SmallBox<MaybeUninit<T>, S, A> {
     #[inline]
-    pub fn try_new_uninit_in(alloc: A) -> Result<Self, AllocError> {
+    pub fn try_new_uninit_in(alloc: A) -> Result<Self, SmallBoxError> {
         Ok(Self(Inner::try_new_uninit_in(alloc)?))
     }
 
@@ -91,12 +146,12 @@ impl<T: Sized, S, A: Allocator> SmallBox<MaybeUninit<T>, S, A> {
     pub fn new_uninit_in(alloc: A) -> Self {
         match Inner::try_new_uninit_in(alloc) {
             Ok(inner) => Self(inner),
-            Err(_) => handle_alloc_error::<T>(()),
+            Err(err) => handle_error(err),
         }
     }
 
     #[inline]
-    pub fn try_new_zeroed_in(alloc: A) -> Result<Self, AllocError> {
+    pub fn try_new_zeroed_in(alloc: A) -> Result<Self, SmallBoxError> {
         Ok(Self(Inner::try_new_zeroed_in(alloc)?))
     }
 
@@ -106,10 +161,13 @@ impl<T: Sized, S, A: Allocator> SmallBox<MaybeUninit<T>, S, A> {
     pub fn new_zeroed_in(alloc: A) -> Self {
         match Inner::try_new_zeroed_in(alloc) {
             Ok(inner) => Self(inner),
-            Err(_) => handle_alloc_error::<T>(()),
+            Err(err) => handle_error(err),
         }
     }
 
+    /// # Safety
+    ///
+    /// The value has to be initialized, as with `MaybeUninit::assume_init`.
     #[inline]
     pub unsafe fn assume_init(self) -> SmallBox<T, S, A> {
         SmallBox(self.0.assume_init())
@@ -124,7 +182,7 @@ impl<T: Sized, S, A: Allocator> SmallBox<MaybeUninit<T>, S, A> {
 
 impl<T: Sized, S, A: Allocator + Default> SmallBox<[MaybeUninit<T>], S, A> {
     #[inline]
-    pub fn try_new_uninit_slice(len: usize) -> Result<Self, AllocError> {
+    pub fn try_new_uninit_slice(len: usize) -> Result<Self, SmallBoxError> {
         Self::try_new_uninit_slice_in(len, A::default())
     }
 
@@ -136,7 +194,7 @@ impl<T: Sized, S, A: Allocator + Default> SmallBox<[MaybeUninit<T>], S, A> {
     }
 
     #[inline]
-    pub fn try_new_zeroed_slice(len: usize) -> Result<Self, AllocError> {
+    pub fn try_new_zeroed_slice(len: usize) -> Result<Self, SmallBoxError> {
         Self::try_new_zeroed_slice_in(len, A::default())
     }
 
@@ -148,9 +206,71 @@ impl<T: Sized, S, A: Allocator + Default> SmallBox<[MaybeUninit<T>], S, A> {
     }
 }
 
+impl<T: Sized, S, A: Allocator + Default> SmallBox<[T], S, A> {
+    #[inline]
+    pub fn try_from_fn<F: FnMut(usize) -> T>(len: usize, f: F) -> Result<Self, SmallBoxError> {
+        Self::try_from_fn_in(len, f, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_fn<F: FnMut(usize) -> T>(len: usize, f: F) -> Self {
+        Self::from_fn_in(len, f, A::default())
+    }
+
+    #[inline]
+    pub fn try_repeat(value: T, n: usize) -> Result<Self, SmallBoxError>
+    where
+        T: Clone,
+    {
+        Self::try_repeat_in(value, n, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn repeat(value: T, n: usize) -> Self
+    where
+        T: Clone,
+    {
+        Self::repeat_in(value, n, A::default())
+    }
+
+    #[inline]
+    pub fn try_from_array<const N: usize>(array: [T; N]) -> Result<Self, SmallBoxError> {
+        Self::try_from_array_in(array, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_array<const N: usize>(array: [T; N]) -> Self {
+        Self::from_array_in(array, A::default())
+    }
+
+    #[inline]
+    pub fn try_init_with<F>(len: usize, f: F) -> Result<Self, SmallBoxError>
+    where
+        F: FnMut(usize, &mut MaybeUninit<T>) -> &mut T,
+    {
+        Self::try_init_with_in(len, f, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn init_with<F>(len: usize, f: F) -> Self
+    where
+        F: FnMut(usize, &mut MaybeUninit<T>) -> &mut T,
+    {
+        Self::init_with_in(len, f, A::default())
+    }
+}
+
 impl<T: Sized, S, A: Allocator> SmallBox<[MaybeUninit<T>], S, A> {
     #[inline]
-    pub fn try_new_uninit_slice_in(len: usize, alloc: A) -> Result<Self, AllocError> {
+    pub fn try_new_uninit_slice_in(len: usize, alloc: A) -> Result<Self, SmallBoxError> {
         Ok(Self(Inner::try_new_uninit_slice_in(len, alloc)?))
     }
 
@@ -160,12 +280,12 @@ impl<T: Sized, S, A: Allocator> SmallBox<[MaybeUninit<T>], S, A> {
     pub fn new_uninit_slice_in(len: usize, alloc: A) -> Self {
         match Inner::try_new_uninit_slice_in(len, alloc) {
             Ok(inner) => Self(inner),
-            Err(_) => handle_alloc_error::<[T]>(len),
+            Err(err) => handle_error(err),
         }
     }
 
     #[inline]
-    pub fn try_new_zeroed_slice_in(len: usize, alloc: A) -> Result<Self, AllocError> {
+    pub fn try_new_zeroed_slice_in(len: usize, alloc: A) -> Result<Self, SmallBoxError> {
         Ok(Self(Inner::try_new_zeroed_slice_in(len, alloc)?))
     }
 
@@ -175,10 +295,13 @@ impl<T: Sized, S, A: Allocator> SmallBox<[MaybeUninit<T>], S, A> {
     pub fn new_zeroed_slice_in(len: usize, alloc: A) -> Self {
         match Inner::try_new_zeroed_slice_in(len, alloc) {
             Ok(inner) => Self(inner),
-            Err(_) => handle_alloc_error::<[T]>(len),
+            Err(err) => handle_error(err),
         }
     }
 
+    /// # Safety
+    ///
+    /// Every element has to be initialized.
     #[inline]
     pub unsafe fn assume_init(self) -> SmallBox<[T], S, A> {
         SmallBox(self.0.assume_init())
@@ -189,7 +312,7 @@ impl<T: Sized, S, A: Allocator> SmallBox<T, S, A> {
     pub const INLINED: bool = Inner::<T, S, A>::inlined(());
 
     #[inline]
-    pub fn try_new_in(value: T, alloc: A) -> Result<Self, AllocError> {
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, SmallBoxError> {
         Ok(SmallBox::try_new_uninit_in(alloc)?.write(value))
     }
 
@@ -199,7 +322,21 @@ impl<T: Sized, S, A: Allocator> SmallBox<T, S, A> {
     pub fn new_in(value: T, alloc: A) -> Self {
         match SmallBox::try_new_uninit_in(alloc) {
             Ok(uninit) => uninit.write(value),
-            Err(_) => handle_alloc_error::<T>(()),
+            Err(err) => handle_error(err),
+        }
+    }
+
+    // fails to compile when the value does not fit inline
+    #[inline]
+    pub const fn new_inline_in(value: T, alloc: A) -> Self {
+        const { assert!(Self::INLINED, "the value does not fit inline") }
+
+        let value = ManuallyDrop::new(value);
+        unsafe {
+            SmallBox(Inner::copy_inline_unchecked(
+                &value as *const _ as *const T,
+                alloc,
+            ))
         }
     }
 
@@ -216,10 +353,205 @@ impl<T: Sized, S, A: Allocator> SmallBox<T, S, A> {
 }
 
 impl<T: Sized, S, A: Allocator> SmallBox<[T], S, A> {
+    // fails to compile when the array does not fit inline
+    #[inline]
+    pub const fn from_array_inline_in<const N: usize>(array: [T; N], alloc: A) -> Self {
+        SmallBox::coerce(SmallBox::new_inline_in(array, alloc))
+    }
+
+    // a panic in `f` drops the elements made so far
+    #[inline]
+    pub fn try_from_fn_in<F: FnMut(usize) -> T>(
+        len: usize,
+        mut f: F,
+        alloc: A,
+    ) -> Result<Self, SmallBoxError> {
+        let mut boxed = SmallBox::<[MaybeUninit<T>], S, A>::try_new_uninit_slice_in(len, alloc)?;
+        let mut guard = Guard {
+            ptr: boxed.as_mut_ptr().cast::<T>(),
+            len: 0,
+        };
+
+        while guard.len < len {
+            unsafe { guard.ptr.add(guard.len).write(f(guard.len)) };
+            guard.len += 1;
+        }
+
+        forget(guard);
+        unsafe { Ok(boxed.assume_init()) }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_fn_in<F: FnMut(usize) -> T>(len: usize, f: F, alloc: A) -> Self {
+        match Self::try_from_fn_in(len, f, alloc) {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    // clones `value` for all but the last element, which gets `value` itself
+    #[inline]
+    pub fn try_repeat_in(value: T, n: usize, alloc: A) -> Result<Self, SmallBoxError>
+    where
+        T: Clone,
+    {
+        let mut value = Some(value);
+
+        Self::try_from_fn_in(
+            n,
+            |i| {
+                let next = if i + 1 < n {
+                    value.clone()
+                } else {
+                    value.take()
+                };
+
+                next.unwrap()
+            },
+            alloc,
+        )
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn repeat_in(value: T, n: usize, alloc: A) -> Self
+    where
+        T: Clone,
+    {
+        match Self::try_repeat_in(value, n, alloc) {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    #[inline]
+    pub fn try_from_array_in<const N: usize>(
+        array: [T; N],
+        alloc: A,
+    ) -> Result<Self, SmallBoxError> {
+        Ok(SmallBox::coerce(SmallBox::try_new_in(array, alloc)?))
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_array_in<const N: usize>(array: [T; N], alloc: A) -> Self {
+        SmallBox::coerce(SmallBox::new_in(array, alloc))
+    }
+
+    // builds the elements in place: `f` gets each slot in turn and has to hand
+    // it back initialized, which `MaybeUninit::write` does. returning anything
+    // else panics
+    #[inline]
+    pub fn try_init_with_in<F>(len: usize, mut f: F, alloc: A) -> Result<Self, SmallBoxError>
+    where
+        F: FnMut(usize, &mut MaybeUninit<T>) -> &mut T,
+    {
+        let mut boxed = SmallBox::<[MaybeUninit<T>], S, A>::try_new_uninit_slice_in(len, alloc)?;
+        let mut guard = Guard {
+            ptr: boxed.as_mut_ptr().cast::<T>(),
+            len: 0,
+        };
+
+        while guard.len < len {
+            let slot = unsafe { &mut *guard.ptr.add(guard.len).cast::<MaybeUninit<T>>() };
+            let ptr = slot.as_mut_ptr();
+
+            assert!(
+                core::ptr::eq(f(guard.len, slot), ptr),
+                "`init_with` has to return the slot it was given"
+            );
+            guard.len += 1;
+        }
+
+        forget(guard);
+        unsafe { Ok(boxed.assume_init()) }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn init_with_in<F>(len: usize, f: F, alloc: A) -> Self
+    where
+        F: FnMut(usize, &mut MaybeUninit<T>) -> &mut T,
+    {
+        match Self::try_init_with_in(len, f, alloc) {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+
     #[inline]
     pub fn uninit_slice(boxed: Self) -> SmallBox<[MaybeUninit<T>], S, A> {
         unsafe { SmallBox(boxed.0.reinterpret_unchecked()) }
     }
+
+    #[inline]
+    pub fn try_truncate(boxed: &mut Self, len: usize) -> Result<(), SmallBoxError> {
+        boxed.0.try_truncate(len)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn truncate(boxed: &mut Self, len: usize) {
+        if let Err(err) = boxed.0.try_truncate(len) {
+            handle_error(err)
+        }
+    }
+
+    // does nothing when `new_len` is not greater than the current length, same
+    // as `try_truncate` with a length that is not smaller
+    #[inline]
+    pub fn try_grow(boxed: &mut Self, new_len: usize, fill: T) -> Result<(), SmallBoxError>
+    where
+        T: Clone,
+    {
+        boxed.0.try_grow_with(new_len, || fill.clone())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn grow(boxed: &mut Self, new_len: usize, fill: T)
+    where
+        T: Clone,
+    {
+        if let Err(err) = Self::try_grow(boxed, new_len, fill) {
+            handle_error(err)
+        }
+    }
+
+    #[inline]
+    pub fn try_clone_from_slice(boxed: &mut Self, src: &[T]) -> Result<(), SmallBoxError>
+    where
+        T: Clone,
+    {
+        boxed.0.try_clone_from_slice(src)
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator + Default> SmallBox<T, S, A> {
+    /// # Safety
+    ///
+    /// Same as [`SmallBox::try_from_unsized_move_in`].
+    #[inline]
+    pub unsafe fn try_from_unsized_move(src: *mut T) -> Result<Self, SmallBoxError> {
+        Self::try_from_unsized_move_in(src, A::default())
+    }
+
+    /// # Safety
+    ///
+    /// Same as [`SmallBox::from_unsized_move_in`].
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub unsafe fn from_unsized_move(src: *mut T) -> Self {
+        Self::from_unsized_move_in(src, A::default())
+    }
 }
 
 impl<T: ?Sized, S, A: Allocator> SmallBox<T, S, A> {
@@ -233,12 +565,25 @@ impl<T: ?Sized, S, A: Allocator> SmallBox<T, S, A> {
         boxed.0.allocator()
     }
 
+    // a const fn can not move the field out of `SmallBox`, not even by destructuring
     #[inline]
-    pub fn coerce<U: ?Sized>(boxed: Self) -> SmallBox<U, S, A>
+    const fn into_raw_inner(boxed: Self) -> Inner<T, S, A> {
+        let boxed = ManuallyDrop::new(boxed);
+        unsafe { read(&(*(&boxed as *const ManuallyDrop<Self> as *const Self)).0) }
+    }
+
+    // also upcasts trait objects, `dyn Sub: Unsize<dyn Super>` holds through
+    // trait upcasting. only the metadata changes, the value stays where it is.
+    // there is no `CoerceUnsized` impl to do this implicitly, since the metadata
+    // field changes its type along with `T`. for the same reason there is no
+    // `DispatchFromDyn` either, and a `SmallBox<Self>` receiver is not dyn
+    // compatible
+    #[inline]
+    pub const fn coerce<U: ?Sized>(boxed: Self) -> SmallBox<U, S, A>
     where
         T: Unsize<U>,
     {
-        SmallBox(boxed.0.coerce())
+        SmallBox(Self::into_raw_inner(boxed).coerce())
     }
 
     #[inline]
@@ -259,6 +604,39 @@ impl<T: ?Sized, S, A: Allocator> SmallBox<T, S, A> {
         }
     }
 
+    /// # Safety
+    ///
+    /// `src` has to point to a valid value. The box takes it over by copying its
+    /// bytes, so on success it must not be used or dropped anymore. On failure it
+    /// is left untouched.
+    #[inline]
+    pub unsafe fn try_from_unsized_move_in(src: *mut T, alloc: A) -> Result<Self, SmallBoxError> {
+        unsafe { Ok(Self(Inner::try_from_unsized_move_in(src, alloc)?)) }
+    }
+
+    /// # Safety
+    ///
+    /// `src` has to point to a valid value, which must not be used or dropped
+    /// after the box took it over.
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub unsafe fn from_unsized_move_in(src: *mut T, alloc: A) -> Self {
+        match unsafe { Self::try_from_unsized_move_in(src, alloc) } {
+            Ok(boxed) => boxed,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `dst` has to be valid for writes of `Layout::for_value(&*boxed)`. The
+    /// returned pointer owns the moved value, dropping it is up to the caller.
+    #[inline]
+    pub unsafe fn into_unsized_move(boxed: Self, dst: *mut u8) -> *mut T {
+        unsafe { Self::into_raw_inner(boxed).into_unsized_move(dst) }
+    }
+
     #[inline]
     #[cfg(feature = "alloc")]
     pub fn from_box(boxed: alloc::boxed::Box<T, A>) -> Self {
@@ -285,22 +663,107 @@ impl<T: ?Sized, S, A: Allocator> SmallBox<T, S, A> {
     }
 }
 
-impl<T: Any + ?Sized, S, A: Allocator> SmallBox<T, S, A> {
+// drops the first `len` elements behind `ptr` if building a slice panics
+struct Guard<T> {
+    ptr: *mut T,
+    len: usize,
+}
+
+impl<T> Drop for Guard<T> {
     #[inline]
-    pub unsafe fn downcast_unchecked<U: Any>(self) -> SmallBox<U, S, A> {
-        SmallBox(self.0.downcast_unchecked::<U>())
+    fn drop(&mut self) {
+        unsafe { drop_in_place(slice_from_raw_parts_mut(self.ptr, self.len)) }
     }
+}
 
-    #[inline]
-    pub fn downcast<U: Any>(self) -> Result<SmallBox<U, S, A>, Self> {
-        if T::type_id(&self) == TypeId::of::<U>() {
-            Ok(unsafe { self.downcast_unchecked() })
-        } else {
-            Err(self)
+// same as `Box`, downcasting is only offered on the `dyn Any` objects themselves,
+// so a box holding another box can not be downcast by accident
+macro_rules! impl_downcast {
+    ($($any:tt)*) => {
+        impl<S, A: Allocator> SmallBox<$($any)*, S, A> {
+            /// # Safety
+            ///
+            /// The box has to hold a `U`.
+            #[inline]
+            pub unsafe fn downcast_unchecked<U: Any>(self) -> SmallBox<U, S, A> {
+                SmallBox(self.0.downcast_unchecked::<U>())
+            }
+
+            #[inline]
+            pub fn downcast<U: Any>(self) -> Result<SmallBox<U, S, A>, Self> {
+                if self.is::<U>() {
+                    Ok(unsafe { self.downcast_unchecked() })
+                } else {
+                    Err(self)
+                }
+            }
+
+            // the box comes back both when it does not hold a `U` and when the
+            // allocation fails, `is` tells the two apart
+            #[inline]
+            #[cfg(feature = "alloc")]
+            pub fn try_downcast_into_box<U: Any>(self) -> Result<alloc::boxed::Box<U, A>, Self> {
+                if !self.is::<U>() {
+                    return Err(self);
+                }
+
+                match self.0.try_into_box() {
+                    Ok(boxed) => {
+                        let (ptr, alloc) = alloc::boxed::Box::into_raw_with_allocator(boxed);
+                        Ok(unsafe { alloc::boxed::Box::from_raw_in(ptr as *mut U, alloc) })
+                    }
+                    Err(inner) => Err(SmallBox(inner)),
+                }
+            }
+
+            #[inline]
+            #[cfg(feature = "alloc")]
+            #[cfg(not(no_global_oom_handling))]
+            pub fn downcast_into_box<U: Any>(self) -> Result<alloc::boxed::Box<U, A>, Self> {
+                if !self.is::<U>() {
+                    return Err(self);
+                }
+
+                match self.try_downcast_into_box::<U>() {
+                    Ok(boxed) => Ok(boxed),
+                    Err(boxed) => handle_alloc_error::<$($any)*>(boxed.0.metadata()),
+                }
+            }
+
+            // same as `try_downcast_into_box`, the box comes back on either error
+            #[inline]
+            pub fn try_downcast_resize<U: Any, Z>(self) -> Result<SmallBox<U, Z, A>, Self> {
+                if !self.is::<U>() {
+                    return Err(self);
+                }
+
+                match self.0.try_resize_stack::<Z>() {
+                    Ok(inner) => Ok(SmallBox(unsafe { inner.downcast_unchecked::<U>() })),
+                    Err(inner) => Err(SmallBox(inner)),
+                }
+            }
+
+            #[inline]
+            #[cfg(feature = "alloc")]
+            #[cfg(not(no_global_oom_handling))]
+            pub fn downcast_resize<U: Any, Z>(self) -> Result<SmallBox<U, Z, A>, Self> {
+                if !self.is::<U>() {
+                    return Err(self);
+                }
+
+                match self.try_downcast_resize::<U, Z>() {
+                    Ok(boxed) => Ok(boxed),
+                    Err(boxed) => handle_alloc_error::<$($any)*>(boxed.0.metadata()),
+                }
+            }
         }
-    }
+    };
 }
 
+impl_downcast!(dyn Any);
+impl_downcast!(dyn Any + Send);
+impl_downcast!(dyn Any + Send + Sync);
+
 impl<T: ?Sized, S, A: Allocator> Deref for SmallBox<T, S, A> {
     type Target = T;
 
@@ -381,6 +844,22 @@ impl<T: Sized, S, A: Allocator + Default> From<T> for SmallBox<T, S, A> {
     }
 }
 
+// same as `Box`, the value stays where it is and only the metadata changes
+impl<T: Sized, S, A: Allocator, const N: usize> TryFrom<SmallBox<[T], S, A>>
+    for SmallBox<[T; N], S, A>
+{
+    type Error = SmallBox<[T], S, A>;
+
+    #[inline]
+    fn try_from(boxed: SmallBox<[T], S, A>) -> Result<Self, Self::Error> {
+        if boxed.len() == N {
+            unsafe { Ok(SmallBox(boxed.0.downcast_unchecked())) }
+        } else {
+            Err(boxed)
+        }
+    }
+}
+
 #[cfg(feature = "alloc")]
 #[cfg(not(no_global_oom_handling))]
 impl<T: Sized + Clone, S, A: Allocator + Clone> Clone for SmallBox<T, S, A> {
@@ -405,10 +884,8 @@ impl<T: Sized + Clone, S, A: Allocator + Clone> Clone for SmallBox<[T], S, A> {
 
     #[inline]
     fn clone_from(&mut self, source: &Self) {
-        if self.len() == source.len() {
-            self.clone_from_slice(source);
-        } else {
-            *self = source.clone();
+        if let Err(err) = Self::try_clone_from_slice(self, source) {
+            handle_error(err)
         }
     }
 }
@@ -418,17 +895,53 @@ impl<T: Sized + Clone, S, A: Allocator + Clone> Clone for SmallBox<[T], S, A> {
 impl<S, A: Allocator + Clone> Clone for SmallBox<str, S, A> {
     #[inline]
     fn clone(&self) -> Self {
-        Self::new_clone_from_in("", Self::allocator(self).clone())
+        Self::new_clone_from_in(&**self, Self::allocator(self).clone())
     }
 
     #[inline]
     fn clone_from(&mut self, source: &Self) {
-        if self.len() == source.len() {
-            unsafe {
-                self.as_bytes_mut().copy_from_slice(source.as_bytes());
+        // the bytes are resized by value and written back by the guard, also when
+        // the allocator panics. the storage is resized first, so an error or a
+        // panic leaves the string as it was
+        struct Guard<'a, S, A: Allocator> {
+            string: &'a mut SmallBox<str, S, A>,
+            bytes: ManuallyDrop<SmallBox<[u8], S, A>>,
+        }
+
+        impl<S, A: Allocator> Drop for Guard<'_, S, A> {
+            #[inline]
+            fn drop(&mut self) {
+                unsafe {
+                    let bytes = ManuallyDrop::take(&mut self.bytes);
+                    core::ptr::write(self.string, SmallBox::from_utf8_unchecked(bytes));
+                }
             }
-        } else {
-            *self = source.clone();
+        }
+
+        let len = source.len();
+
+        let result = unsafe {
+            let bytes = read(self).into_bytes();
+            let mut guard = Guard {
+                string: self,
+                bytes: ManuallyDrop::new(bytes),
+            };
+
+            let result = if len > guard.bytes.len() {
+                SmallBox::try_grow(&mut guard.bytes, len, 0)
+            } else {
+                SmallBox::try_truncate(&mut guard.bytes, len)
+            };
+
+            if result.is_ok() {
+                guard.bytes.copy_from_slice(source.as_bytes());
+            }
+
+            result
+        };
+
+        if let Err(err) = result {
+            handle_error(err)
         }
     }
 }
@@ -509,7 +1022,7 @@ pub trait CloneFrom<T: ?Sized, A: Allocator>
 where
     Self: Sized,
 {
-    fn try_new_clone_from_in(data: &T, alloc: A) -> Result<Self, AllocError>;
+    fn try_new_clone_from_in(data: &T, alloc: A) -> Result<Self, SmallBoxError>;
 
     #[inline]
     #[cfg(feature = "alloc")]
@@ -517,30 +1030,30 @@ where
     fn new_clone_from_in(data: &T, alloc: A) -> Self {
         match Self::try_new_clone_from_in(data, alloc) {
             Ok(boxed) => boxed,
-            Err(_) => handle_alloc_error::<T>((data as *const T).to_raw_parts().1),
+            Err(err) => handle_error(err),
         }
     }
 }
 
 impl<T: Sized + Clone, S, A: Allocator> CloneFrom<T, A> for SmallBox<T, S, A> {
     #[inline]
-    fn try_new_clone_from_in(data: &T, alloc: A) -> Result<Self, AllocError> {
+    fn try_new_clone_from_in(data: &T, alloc: A) -> Result<Self, SmallBoxError> {
         Self::try_new_in(data.clone(), alloc)
     }
 }
 
 impl<T: Sized + Clone, S, A: Allocator> CloneFrom<[T], A> for SmallBox<[T], S, A> {
     #[inline]
-    fn try_new_clone_from_in(data: &[T], alloc: A) -> Result<Self, AllocError> {
+    fn try_new_clone_from_in(data: &[T], alloc: A) -> Result<Self, SmallBoxError> {
         let mut boxed = SmallBox::try_new_uninit_slice_in(data.len(), alloc)?;
-        MaybeUninit::write_slice_cloned(&mut boxed, data);
+        boxed.write_clone_of_slice(data);
         unsafe { Ok(boxed.assume_init()) }
     }
 }
 
 impl<S, A: Allocator> CloneFrom<str, A> for SmallBox<str, S, A> {
     #[inline]
-    fn try_new_clone_from_in(data: &str, alloc: A) -> Result<Self, AllocError> {
+    fn try_new_clone_from_in(data: &str, alloc: A) -> Result<Self, SmallBoxError> {
         Self::try_new_copy_from_in(data, alloc)
     }
 }
@@ -549,7 +1062,7 @@ pub trait CopyFrom<T: ?Sized, A: Allocator>
 where
     Self: Sized,
 {
-    fn try_new_copy_from_in(data: &T, alloc: A) -> Result<Self, AllocError>;
+    fn try_new_copy_from_in(data: &T, alloc: A) -> Result<Self, SmallBoxError>;
 
     #[inline]
     #[cfg(feature = "alloc")]
@@ -557,33 +1070,33 @@ where
     fn new_copy_from_in(data: &T, alloc: A) -> Self {
         match Self::try_new_copy_from_in(data, alloc) {
             Ok(boxed) => boxed,
-            Err(_) => handle_alloc_error::<T>((data as *const T).to_raw_parts().1),
+            Err(err) => handle_error(err),
         }
     }
 }
 
 impl<T: Sized + Copy, S, A: Allocator> CopyFrom<T, A> for SmallBox<T, S, A> {
     #[inline]
-    fn try_new_copy_from_in(data: &T, alloc: A) -> Result<Self, AllocError> {
+    fn try_new_copy_from_in(data: &T, alloc: A) -> Result<Self, SmallBoxError> {
         Self::try_new_in(*data, alloc)
     }
 }
 
 impl<T: Sized + Copy, S, A: Allocator> CopyFrom<[T], A> for SmallBox<[T], S, A> {
     #[inline]
-    fn try_new_copy_from_in(data: &[T], alloc: A) -> Result<Self, AllocError> {
+    fn try_new_copy_from_in(data: &[T], alloc: A) -> Result<Self, SmallBoxError> {
         let mut boxed = SmallBox::try_new_uninit_slice_in(data.len(), alloc)?;
-        MaybeUninit::write_slice(&mut boxed, data);
+        boxed.write_copy_of_slice(data);
         unsafe { Ok(boxed.assume_init()) }
     }
 }
 
 impl<S, A: Allocator> CopyFrom<str, A> for SmallBox<str, S, A> {
     #[inline]
-    fn try_new_copy_from_in(data: &str, alloc: A) -> Result<Self, AllocError> {
+    fn try_new_copy_from_in(data: &str, alloc: A) -> Result<Self, SmallBoxError> {
         let mut boxed =
             SmallBox::<[MaybeUninit<u8>], _, _>::try_new_uninit_slice_in(data.len(), alloc)?;
-        MaybeUninit::write_slice(&mut boxed, data.as_bytes());
+        boxed.write_copy_of_slice(data.as_bytes());
         unsafe { Ok(Self::from_utf8_unchecked(boxed.assume_init())) }
     }
 }
@@ -593,7 +1106,7 @@ where
     Self: CloneFrom<T, A>,
 {
     #[inline]
-    pub fn try_clone_from(data: &T) -> Result<Self, AllocError> {
+    pub fn try_clone_from(data: &T) -> Result<Self, SmallBoxError> {
         Self::try_new_clone_from_in(data, A::default())
     }
 
@@ -610,7 +1123,7 @@ where
     Self: CopyFrom<T, A>,
 {
     #[inline]
-    pub fn try_copy_from(data: &T) -> Result<Self, AllocError> {
+    pub fn try_copy_from(data: &T) -> Result<Self, SmallBoxError> {
         Self::try_new_copy_from_in(data, A::default())
     }
 
@@ -623,6 +1136,21 @@ where
 }
 
 impl<S, A: Allocator> SmallBox<str, S, A> {
+    // a string only known at runtime can not be checked at compile time, so
+    // this panics instead, which in a const context is a compile error as well
+    #[inline]
+    pub const fn from_str_inline_in(value: &str, alloc: A) -> Self {
+        assert!(
+            Inner::<str, S, A>::inlined(value.len()),
+            "the string does not fit inline"
+        );
+
+        unsafe { SmallBox(Inner::copy_inline_unchecked(value, alloc)) }
+    }
+
+    /// # Safety
+    ///
+    /// The bytes have to be valid UTF-8.
     #[inline]
     pub unsafe fn from_utf8_unchecked(boxed: SmallBox<[u8], S, A>) -> Self {
         unsafe { Self(boxed.0.reinterpret_unchecked()) }
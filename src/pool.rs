@@ -0,0 +1,206 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::UnsafeCell,
+    hint::spin_loop,
+    mem::MaybeUninit,
+    ptr::{null_mut, NonNull},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use crate::Owns;
+
+// size classes are powers of two from 16 bytes up to 4 KiB
+const MIN_CLASS_SHIFT: u32 = 4;
+const MAX_CLASS_SHIFT: u32 = 12;
+const CLASSES: usize = (MAX_CLASS_SHIFT - MIN_CLASS_SHIFT + 1) as usize;
+
+// alignment of the backing memory, requests aligned above this always fail
+const MAX_ALIGN: usize = 64;
+
+pub struct PoolAllocator<const SIZE: usize> {
+    lock: AtomicBool,
+    state: UnsafeCell<State>,
+    memory: UnsafeCell<Memory<SIZE>>,
+}
+
+struct State {
+    // bytes of `memory` handed out to a size class so far
+    used: usize,
+    // heads of the intrusive free lists, one per size class
+    free: [*mut FreeBlock; CLASSES],
+}
+
+struct FreeBlock {
+    next: *mut FreeBlock,
+}
+
+#[repr(C, align(64))]
+struct Memory<const SIZE: usize>([MaybeUninit<u8>; SIZE]);
+
+impl<const SIZE: usize> PoolAllocator<SIZE> {
+    pub const MAX_BLOCK_SIZE: usize = 1 << MAX_CLASS_SHIFT;
+
+    #[inline]
+    pub const fn new() -> Self {
+        Self {
+            lock: AtomicBool::new(false),
+            state: UnsafeCell::new(State {
+                used: 0,
+                free: [null_mut(); CLASSES],
+            }),
+            memory: UnsafeCell::new(Memory([MaybeUninit::uninit(); SIZE])),
+        }
+    }
+
+    #[inline]
+    fn class_of(layout: Layout) -> Option<usize> {
+        if layout.align() > MAX_ALIGN {
+            return None;
+        }
+
+        let size = layout.size().max(layout.align()).max(1 << MIN_CLASS_SHIFT);
+        let shift = size.checked_next_power_of_two()?.trailing_zeros();
+
+        if shift > MAX_CLASS_SHIFT {
+            None
+        } else {
+            Some((shift - MIN_CLASS_SHIFT) as usize)
+        }
+    }
+
+    #[inline]
+    const fn class_size(class: usize) -> usize {
+        1 << (class as u32 + MIN_CLASS_SHIFT)
+    }
+
+    #[inline]
+    fn lock(&self) -> Guard<'_, SIZE> {
+        while self
+            .lock
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            spin_loop();
+        }
+
+        Guard(self)
+    }
+}
+
+impl<const SIZE: usize> Owns for PoolAllocator<SIZE> {
+    #[inline]
+    fn owns(&self, ptr: NonNull<u8>) -> bool {
+        let start = self.memory.get() as usize;
+        (start..start + SIZE).contains(&(ptr.as_ptr() as usize))
+    }
+}
+
+impl<const SIZE: usize> Default for PoolAllocator<SIZE> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// only implemented for references: the blocks live inside the pool, so a box
+// holding the pool by value would point into it and dangle once moved
+unsafe impl<const SIZE: usize> Allocator for &PoolAllocator<SIZE> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let class = PoolAllocator::<SIZE>::class_of(layout).ok_or(AllocError)?;
+        let size = PoolAllocator::<SIZE>::class_size(class);
+        let _guard = self.lock();
+        let state = unsafe { &mut *self.state.get() };
+
+        let ptr = if let Some(block) = NonNull::new(state.free[class]) {
+            state.free[class] = unsafe { block.as_ref().next };
+            block.cast::<u8>()
+        } else {
+            // blocks are carved at a multiple of their own size (capped by
+            // the alignment of the memory), which satisfies every alignment
+            // that maps to this class
+            let offset = state.used.next_multiple_of(size.min(MAX_ALIGN));
+
+            if offset > SIZE || SIZE - offset < size {
+                return Err(AllocError);
+            }
+
+            state.used = offset + size;
+            unsafe { NonNull::new_unchecked((self.memory.get() as *mut u8).add(offset)) }
+        };
+
+        Ok(NonNull::slice_from_raw_parts(ptr, size))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let class = match PoolAllocator::<SIZE>::class_of(layout) {
+            Some(class) => class,
+            None => return,
+        };
+
+        let _guard = self.lock();
+        let state = &mut *self.state.get();
+        let block = ptr.as_ptr() as *mut FreeBlock;
+
+        block.write(FreeBlock {
+            next: state.free[class],
+        });
+
+        state.free[class] = block;
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match PoolAllocator::<SIZE>::class_of(new_layout) {
+            // the block is already big enough
+            Some(class) if Some(class) == PoolAllocator::<SIZE>::class_of(old_layout) => Ok(
+                NonNull::slice_from_raw_parts(ptr, PoolAllocator::<SIZE>::class_size(class)),
+            ),
+
+            _ => {
+                let new = self.allocate(new_layout)?;
+                ptr.as_ptr()
+                    .copy_to_nonoverlapping(new.as_ptr() as *mut u8, old_layout.size());
+                self.deallocate(ptr, old_layout);
+                Ok(new)
+            }
+        }
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        match PoolAllocator::<SIZE>::class_of(new_layout) {
+            Some(class) if Some(class) == PoolAllocator::<SIZE>::class_of(old_layout) => Ok(
+                NonNull::slice_from_raw_parts(ptr, PoolAllocator::<SIZE>::class_size(class)),
+            ),
+
+            _ => {
+                let new = self.allocate(new_layout)?;
+                ptr.as_ptr()
+                    .copy_to_nonoverlapping(new.as_ptr() as *mut u8, new_layout.size());
+                self.deallocate(ptr, old_layout);
+                Ok(new)
+            }
+        }
+    }
+}
+
+// the state and memory are only touched while holding the lock
+unsafe impl<const SIZE: usize> Send for PoolAllocator<SIZE> {}
+unsafe impl<const SIZE: usize> Sync for PoolAllocator<SIZE> {}
+
+struct Guard<'a, const SIZE: usize>(&'a PoolAllocator<SIZE>);
+
+impl<const SIZE: usize> Drop for Guard<'_, SIZE> {
+    #[inline]
+    fn drop(&mut self) {
+        self.0.lock.store(false, Ordering::Release);
+    }
+}
@@ -0,0 +1,94 @@
+use core::{alloc::Allocator, fmt, marker::PhantomData, mem::size_of, str::from_utf8};
+
+use ::serde::{
+    de::{Error, SeqAccess, Unexpected, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::{CopyFrom, SmallBox, SmallBoxVec};
+
+// upper bound on the preallocation done from an untrusted `size_hint`
+const MAX_PREALLOC_BYTES: usize = 1024 * 1024;
+
+impl<T: ?Sized + Serialize, S, A: Allocator> Serialize for SmallBox<T, S, A> {
+    #[inline]
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>, S, A: Allocator + Default> Deserialize<'de> for SmallBox<T, S, A> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = T::deserialize(deserializer)?;
+        SmallBox::try_new(value).map_err(D::Error::custom)
+    }
+}
+
+impl<'de, S, A: Allocator + Default> Deserialize<'de> for SmallBox<str, S, A> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor(PhantomData))
+    }
+}
+
+impl<'de, T: Deserialize<'de>, S, A: Allocator + Default> Deserialize<'de> for SmallBox<[T], S, A> {
+    #[inline]
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(SliceVisitor(PhantomData))
+    }
+}
+
+struct StrVisitor<S, A>(PhantomData<(S, A)>);
+
+impl<'de, S, A: Allocator + Default> Visitor<'de> for StrVisitor<S, A> {
+    type Value = SmallBox<str, S, A>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+
+    #[inline]
+    fn visit_str<E: Error>(self, v: &str) -> Result<Self::Value, E> {
+        SmallBox::try_new_copy_from_in(v, A::default()).map_err(E::custom)
+    }
+
+    #[inline]
+    fn visit_bytes<E: Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        match from_utf8(v) {
+            Ok(v) => self.visit_str(v),
+            Err(_) => Err(E::invalid_value(Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+struct SliceVisitor<T, S, A>(PhantomData<(T, S, A)>);
+
+impl<'de, T: Deserialize<'de>, S, A: Allocator + Default> Visitor<'de> for SliceVisitor<T, S, A> {
+    type Value = SmallBox<[T], S, A>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a sequence")
+    }
+
+    fn visit_seq<V: SeqAccess<'de>>(self, mut seq: V) -> Result<Self::Value, V::Error> {
+        let mut vec = SmallBoxVec::<T, S, A>::new();
+
+        // short sequences stay in the inline space of the vector and are
+        // handed over to the box without touching the allocator
+        let hint = seq.size_hint().unwrap_or(0);
+        let hint = hint.min(MAX_PREALLOC_BYTES / size_of::<T>().max(1));
+        vec.try_reserve(hint).map_err(V::Error::custom)?;
+
+        while let Some(value) = seq.next_element()? {
+            vec.try_reserve(1).map_err(V::Error::custom)?;
+
+            if vec.push_within_capacity(value).is_err() {
+                unreachable!()
+            }
+        }
+
+        vec.try_into_boxed_slice()
+            .map_err(|_| V::Error::custom("memory allocation failed"))
+    }
+}
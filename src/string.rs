@@ -0,0 +1,470 @@
+use core::{
+    alloc::Allocator,
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, DerefMut},
+    ptr::{copy, copy_nonoverlapping},
+    str::{from_utf8, from_utf8_unchecked, from_utf8_unchecked_mut, Utf8Error},
+};
+
+use crate::{SmallBox, SmallBoxError, SmallBoxVec};
+
+#[cfg(feature = "alloc")]
+pub struct SmallString<Space, A: Allocator = alloc::alloc::Global> {
+    vec: SmallBoxVec<u8, Space, A>,
+}
+
+#[cfg(not(feature = "alloc"))]
+pub struct SmallString<Space, A: Allocator> {
+    vec: SmallBoxVec<u8, Space, A>,
+}
+
+impl<S, A: Allocator + Default> SmallString<S, A> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(A::default())
+    }
+
+    #[inline]
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, SmallBoxError> {
+        Self::try_with_capacity_in(capacity, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, A::default())
+    }
+}
+
+impl<S, A: Allocator> SmallString<S, A> {
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        Self {
+            vec: SmallBoxVec::new_in(alloc),
+        }
+    }
+
+    #[inline]
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, SmallBoxError> {
+        Ok(Self {
+            vec: SmallBoxVec::try_with_capacity_in(capacity, alloc)?,
+        })
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        Self {
+            vec: SmallBoxVec::with_capacity_in(capacity, alloc),
+        }
+    }
+
+    #[inline]
+    pub fn from_utf8(
+        vec: SmallBoxVec<u8, S, A>,
+    ) -> Result<Self, (SmallBoxVec<u8, S, A>, Utf8Error)> {
+        match from_utf8(&vec) {
+            Ok(_) => Ok(Self { vec }),
+            Err(err) => Err((vec, err)),
+        }
+    }
+
+    /// # Safety
+    ///
+    /// The bytes of `vec` have to be valid UTF-8.
+    #[inline]
+    pub unsafe fn from_utf8_unchecked(vec: SmallBoxVec<u8, S, A>) -> Self {
+        Self { vec }
+    }
+
+    #[inline]
+    pub fn into_bytes(self) -> SmallBoxVec<u8, S, A> {
+        self.vec
+    }
+
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        unsafe { from_utf8_unchecked(&self.vec) }
+    }
+
+    #[inline]
+    pub fn as_mut_str(&mut self) -> &mut str {
+        unsafe { from_utf8_unchecked_mut(&mut self.vec) }
+    }
+
+    /// # Safety
+    ///
+    /// The bytes have to be valid UTF-8 again by the time the borrow ends.
+    #[inline]
+    pub unsafe fn as_mut_vec(&mut self) -> &mut SmallBoxVec<u8, S, A> {
+        &mut self.vec
+    }
+
+    #[inline]
+    pub fn is_inlined(&self) -> bool {
+        self.vec.is_inlined()
+    }
+
+    #[inline]
+    pub fn allocator(&self) -> &A {
+        self.vec.allocator()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.vec.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), SmallBoxError> {
+        self.vec.try_reserve(additional)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional)
+    }
+
+    #[inline]
+    pub fn try_shrink_to_fit(&mut self) -> Result<(), SmallBoxError> {
+        self.vec.try_shrink_to_fit()
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn shrink_to_fit(&mut self) {
+        self.vec.shrink_to_fit()
+    }
+
+    #[inline]
+    pub fn try_push_str(&mut self, string: &str) -> Result<(), SmallBoxError> {
+        self.vec.try_extend_from_slice(string.as_bytes())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn push_str(&mut self, string: &str) {
+        self.vec.extend_from_slice(string.as_bytes())
+    }
+
+    #[inline]
+    pub fn try_push(&mut self, ch: char) -> Result<(), SmallBoxError> {
+        self.try_push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<char> {
+        let ch = self.chars().next_back()?;
+        self.vec.truncate(self.len() - ch.len_utf8());
+        Some(ch)
+    }
+
+    #[inline]
+    pub fn try_insert_str(&mut self, index: usize, string: &str) -> Result<(), SmallBoxError> {
+        assert!(self.is_char_boundary(index), "index is not a char boundary");
+
+        let len = self.len();
+        let amount = string.len();
+        self.vec.try_reserve(amount)?;
+
+        unsafe {
+            let ptr = self.vec.as_mut_ptr().add(index);
+            copy(ptr, ptr.add(amount), len - index);
+            copy_nonoverlapping(string.as_ptr(), ptr, amount);
+            self.vec.set_len(len + amount);
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn insert_str(&mut self, index: usize, string: &str) {
+        assert!(self.is_char_boundary(index), "index is not a char boundary");
+
+        self.vec.reserve(string.len());
+
+        if self.try_insert_str(index, string).is_err() {
+            unreachable!()
+        }
+    }
+
+    #[inline]
+    pub fn try_insert(&mut self, index: usize, ch: char) -> Result<(), SmallBoxError> {
+        self.try_insert_str(index, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn insert(&mut self, index: usize, ch: char) {
+        self.insert_str(index, ch.encode_utf8(&mut [0; 4]))
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: usize) -> char {
+        let ch = match self[index..].chars().next() {
+            Some(ch) => ch,
+            None => panic!("cannot remove a char from the end of a string"),
+        };
+
+        self.vec.drain(index..index + ch.len_utf8());
+        ch
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len() {
+            assert!(
+                self.is_char_boundary(len),
+                "new length is not a char boundary"
+            );
+            self.vec.truncate(len)
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vec.clear()
+    }
+
+    #[inline]
+    pub fn try_into_boxed_str(self) -> Result<SmallBox<str, S, A>, Self> {
+        match self.vec.try_into_boxed_slice() {
+            Ok(boxed) => Ok(unsafe { SmallBox::from_utf8_unchecked(boxed) }),
+            Err(vec) => Err(Self { vec }),
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn into_boxed_str(self) -> SmallBox<str, S, A> {
+        unsafe { SmallBox::from_utf8_unchecked(self.vec.into_boxed_slice()) }
+    }
+}
+
+impl<S, A: Allocator> From<SmallBox<str, S, A>> for SmallString<S, A> {
+    #[inline]
+    fn from(boxed: SmallBox<str, S, A>) -> Self {
+        Self {
+            vec: boxed.into_bytes().into_vec(),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<S, A: Allocator> From<SmallString<S, A>> for SmallBox<str, S, A> {
+    #[inline]
+    fn from(string: SmallString<S, A>) -> Self {
+        string.into_boxed_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<S, A: Allocator + Default> From<&str> for SmallString<S, A> {
+    #[inline]
+    fn from(string: &str) -> Self {
+        let mut result = Self::new();
+        result.push_str(string);
+        result
+    }
+}
+
+impl<S, A: Allocator> SmallBox<str, S, A> {
+    #[inline]
+    pub fn into_string(self) -> SmallString<S, A> {
+        SmallString::from(self)
+    }
+}
+
+impl<S, A: Allocator> Deref for SmallString<S, A> {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<S, A: Allocator> DerefMut for SmallString<S, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_str()
+    }
+}
+
+impl<S, A: Allocator> AsRef<str> for SmallString<S, A> {
+    #[inline]
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<S, A: Allocator> AsRef<[u8]> for SmallString<S, A> {
+    #[inline]
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl<S, A: Allocator + Default> Default for SmallString<S, A> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S, A: Allocator> fmt::Write for SmallString<S, A> {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_push_str(s).map_err(|_| fmt::Error)
+    }
+
+    #[inline]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.try_push(c).map_err(|_| fmt::Error)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<S, A: Allocator> Extend<char> for SmallString<S, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for ch in iter {
+            self.push(ch);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<'a, S, A: Allocator> Extend<&'a char> for SmallString<S, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = &'a char>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<'a, S, A: Allocator> Extend<&'a str> for SmallString<S, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        for string in iter {
+            self.push_str(string);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<S, A: Allocator + Default> FromIterator<char> for SmallString<S, A> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = char>>(iter: I) -> Self {
+        let mut string = Self::new();
+        string.extend(iter);
+        string
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<S, A: Allocator + Clone> Clone for SmallString<S, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Self {
+            vec: self.vec.clone(),
+        }
+    }
+}
+
+impl<S, A: Allocator> fmt::Display for SmallString<S, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<S, A: Allocator> fmt::Debug for SmallString<S, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<S, A: Allocator> PartialEq for SmallString<S, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        PartialEq::eq(&**self, &**other)
+    }
+}
+
+impl<S, A: Allocator> PartialEq<str> for SmallString<S, A> {
+    #[inline]
+    fn eq(&self, other: &str) -> bool {
+        PartialEq::eq(&**self, other)
+    }
+}
+
+impl<S, A: Allocator> PartialEq<&str> for SmallString<S, A> {
+    #[inline]
+    fn eq(&self, other: &&str) -> bool {
+        PartialEq::eq(&**self, *other)
+    }
+}
+
+impl<S, A: Allocator> Eq for SmallString<S, A> {}
+
+impl<S, A: Allocator> PartialOrd for SmallString<S, A> {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S, A: Allocator> Ord for SmallString<S, A> {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        Ord::cmp(&**self, &**other)
+    }
+}
+
+impl<S, A: Allocator> Hash for SmallString<S, A> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
@@ -0,0 +1,317 @@
+use alloc::{alloc::Global, vec::Vec};
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::{Cell, RefCell},
+    ptr::NonNull,
+};
+
+pub struct FailingAllocator<A: Allocator = Global> {
+    inner: A,
+    attempts: Cell<usize>,
+    fail_nth: Option<usize>,
+    fail_above: Option<usize>,
+}
+
+impl<A: Allocator + Default> FailingAllocator<A> {
+    #[inline]
+    pub fn fail_nth(n: usize) -> Self {
+        Self::fail_nth_in(n, A::default())
+    }
+
+    #[inline]
+    pub fn fail_above(size: usize) -> Self {
+        Self::fail_above_in(size, A::default())
+    }
+}
+
+impl<A: Allocator> FailingAllocator<A> {
+    // the `n`th allocation attempt (counting from zero) fails, every other succeeds
+    #[inline]
+    pub const fn fail_nth_in(n: usize, alloc: A) -> Self {
+        Self {
+            inner: alloc,
+            attempts: Cell::new(0),
+            fail_nth: Some(n),
+            fail_above: None,
+        }
+    }
+
+    // every allocation attempt of more than `size` bytes fails
+    #[inline]
+    pub const fn fail_above_in(size: usize, alloc: A) -> Self {
+        Self {
+            inner: alloc,
+            attempts: Cell::new(0),
+            fail_nth: None,
+            fail_above: Some(size),
+        }
+    }
+
+    #[inline]
+    pub fn attempts(&self) -> usize {
+        self.attempts.get()
+    }
+
+    #[inline]
+    fn check(&self, layout: Layout) -> Result<(), AllocError> {
+        let attempt = self.attempts.get();
+        self.attempts.set(attempt + 1);
+
+        if self.fail_nth == Some(attempt)
+            || self.fail_above.is_some_and(|size| layout.size() > size)
+        {
+            Err(AllocError)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for FailingAllocator<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(layout)?;
+        self.inner.allocate(layout)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(layout)?;
+        self.inner.allocate_zeroed(layout)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.inner.deallocate(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.grow(ptr, old_layout, new_layout)
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.grow_zeroed(ptr, old_layout, new_layout)
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.check(new_layout)?;
+        self.inner.shrink(ptr, old_layout, new_layout)
+    }
+}
+
+pub struct TrackingAllocator<A: Allocator = Global> {
+    inner: A,
+    live: RefCell<Vec<Block>>,
+    allocations: Cell<usize>,
+}
+
+#[derive(Clone, Copy)]
+struct Block {
+    ptr: NonNull<u8>,
+    layout: Layout,
+    // size of the block actually returned, deallocation may use any size in
+    // between the requested and the returned one
+    size: usize,
+}
+
+impl<A: Allocator + Default> TrackingAllocator<A> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(A::default())
+    }
+}
+
+impl<A: Allocator + Default> Default for TrackingAllocator<A> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<A: Allocator> TrackingAllocator<A> {
+    #[inline]
+    pub const fn new_in(alloc: A) -> Self {
+        Self {
+            inner: alloc,
+            live: RefCell::new(Vec::new()),
+            allocations: Cell::new(0),
+        }
+    }
+
+    #[inline]
+    pub fn live(&self) -> usize {
+        self.live.borrow().len()
+    }
+
+    #[inline]
+    pub fn allocations(&self) -> usize {
+        self.allocations.get()
+    }
+
+    #[inline]
+    pub fn live_bytes(&self) -> usize {
+        self.live
+            .borrow()
+            .iter()
+            .map(|block| block.layout.size())
+            .sum()
+    }
+
+    #[inline]
+    fn insert(&self, block: NonNull<[u8]>, layout: Layout) {
+        self.allocations.set(self.allocations.get() + 1);
+        self.live.borrow_mut().push(Block {
+            ptr: block.cast(),
+            layout,
+            size: block.len(),
+        });
+    }
+
+    #[track_caller]
+    fn remove(&self, ptr: NonNull<u8>, layout: Layout) {
+        let mut live = self.live.borrow_mut();
+
+        let index = match live.iter().position(|block| block.ptr == ptr) {
+            Some(index) => index,
+            None => panic!("double free or deallocation of an unknown block at {ptr:p}"),
+        };
+
+        let block = live.swap_remove(index);
+
+        if block.layout.align() != layout.align()
+            || layout.size() < block.layout.size()
+            || layout.size() > block.size
+        {
+            panic!(
+                "block at {ptr:p} was allocated with {:?} but deallocated with {layout:?}",
+                block.layout,
+            );
+        }
+    }
+
+    // a failed resize leaves the old block alive, so it is tracked again
+    #[inline]
+    fn resize(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+        resize: impl FnOnce(NonNull<u8>) -> Result<NonNull<[u8]>, AllocError>,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        let old = self
+            .live
+            .borrow()
+            .iter()
+            .find(|block| block.ptr == ptr)
+            .copied();
+
+        self.remove(ptr, old_layout);
+
+        match resize(ptr) {
+            Ok(block) => {
+                self.insert(block, new_layout);
+                Ok(block)
+            }
+
+            Err(err) => {
+                if let Some(old) = old {
+                    self.live.borrow_mut().push(old);
+                }
+
+                Err(err)
+            }
+        }
+    }
+}
+
+unsafe impl<A: Allocator> Allocator for TrackingAllocator<A> {
+    #[inline]
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.inner.allocate(layout)?;
+        self.insert(block, layout);
+        Ok(block)
+    }
+
+    #[inline]
+    fn allocate_zeroed(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let block = self.inner.allocate_zeroed(layout)?;
+        self.insert(block, layout);
+        Ok(block)
+    }
+
+    #[inline]
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.remove(ptr, layout);
+        self.inner.deallocate(ptr, layout)
+    }
+
+    #[inline]
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, |ptr| {
+            self.inner.grow(ptr, old_layout, new_layout)
+        })
+    }
+
+    #[inline]
+    unsafe fn grow_zeroed(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, |ptr| {
+            self.inner.grow_zeroed(ptr, old_layout, new_layout)
+        })
+    }
+
+    #[inline]
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        self.resize(ptr, old_layout, new_layout, |ptr| {
+            self.inner.shrink(ptr, old_layout, new_layout)
+        })
+    }
+}
+
+impl<A: Allocator> Drop for TrackingAllocator<A> {
+    fn drop(&mut self) {
+        let live = self.live.get_mut();
+
+        if !live.is_empty() && !std::thread::panicking() {
+            panic!(
+                "{} block(s) leaked, {} byte(s) in total",
+                live.len(),
+                live.iter().map(|block| block.layout.size()).sum::<usize>(),
+            );
+        }
+    }
+}
@@ -0,0 +1,413 @@
+use core::{
+    alloc::{Allocator, Layout},
+    fmt,
+    marker::{PhantomData, Unsize},
+    mem::{forget, size_of, ManuallyDrop},
+    num::NonZero,
+    ops::{Deref, DerefMut},
+    panic::{RefUnwindSafe, UnwindSafe},
+    ptr::{
+        copy_nonoverlapping, drop_in_place, from_raw_parts, from_raw_parts_mut, metadata, read,
+        read_unaligned, write_unaligned, NonNull, Pointee,
+    },
+};
+
+#[cfg(feature = "alloc")]
+use crate::inner::{handle_alloc_error, handle_error};
+use crate::{
+    inner::{layout_from_metadata, Data, Heap, Inner, Stack},
+    SmallBox, SmallBoxError,
+};
+
+// the address of inline values. no allocation can start there: anything
+// aligned to more than 1 can not, and anything else is at least a byte long and
+// would wrap around the address space
+const INLINE: NonNull<u8> = NonNull::without_provenance(NonZero::<usize>::MAX);
+
+#[cfg(feature = "alloc")]
+pub struct ThinSmallBox<T: ?Sized, Space, A: Allocator = alloc::alloc::Global> {
+    phantom: PhantomData<T>,
+    // `INLINE` when the value lives in `stack`, with the metadata in its last
+    // bytes. otherwise the value on the heap, with the metadata right in front
+    // of it, which keeps the address from ever being `INLINE` as well
+    ptr: NonNull<u8>,
+    stack: Stack<Space>,
+    alloc: A,
+}
+
+#[cfg(not(feature = "alloc"))]
+pub struct ThinSmallBox<T: ?Sized, Space, A: Allocator> {
+    phantom: PhantomData<T>,
+    ptr: NonNull<u8>,
+    stack: Stack<Space>,
+    alloc: A,
+}
+
+impl<T: Sized, S, A: Allocator + Default> ThinSmallBox<T, S, A> {
+    #[inline]
+    pub fn try_new(value: T) -> Result<Self, SmallBoxError> {
+        Self::try_new_in(value, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn new(value: T) -> Self {
+        Self::new_in(value, A::default())
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator + Default> ThinSmallBox<T, S, A> {
+    #[inline]
+    pub fn try_new_unsize<U: Unsize<T>>(value: U) -> Result<Self, SmallBoxError> {
+        Self::try_new_unsize_in(value, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn new_unsize<U: Unsize<T>>(value: U) -> Self {
+        Self::new_unsize_in(value, A::default())
+    }
+}
+
+impl<T: Sized, S, A: Allocator> ThinSmallBox<T, S, A> {
+    #[inline]
+    pub fn try_new_in(value: T, alloc: A) -> Result<Self, SmallBoxError> {
+        let this = unsafe { Self::try_from_raw_in(&value, alloc)? };
+        forget(value);
+        Ok(this)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn new_in(value: T, alloc: A) -> Self {
+        match Self::try_new_in(value, alloc) {
+            Ok(this) => this,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    #[inline]
+    pub fn into_inner(boxed: Self) -> T {
+        let mut this = ManuallyDrop::new(boxed);
+
+        unsafe {
+            let value = read(this.as_ptr());
+            this.deallocate();
+            drop_in_place(&mut this.alloc);
+            value
+        }
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> ThinSmallBox<T, S, A> {
+    // the metadata takes the last bytes of the inline space, so the value
+    // can start at its beginning
+    const TAIL: usize = size_of::<S>().saturating_sub(size_of::<<T as Pointee>::Metadata>());
+
+    #[inline]
+    pub fn try_new_unsize_in<U: Unsize<T>>(value: U, alloc: A) -> Result<Self, SmallBoxError> {
+        let this = unsafe { Self::try_from_raw_in(&value as *const U as *const T, alloc)? };
+        forget(value);
+        Ok(this)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn new_unsize_in<U: Unsize<T>>(value: U, alloc: A) -> Self {
+        match Self::try_new_unsize_in(value, alloc) {
+            Ok(this) => this,
+            Err(err) => handle_error(err),
+        }
+    }
+
+    #[inline]
+    pub fn try_from_small(boxed: SmallBox<T, S, A>) -> Result<Self, SmallBox<T, S, A>> {
+        let (data, metadata, alloc) = boxed.0.into_parts();
+
+        unsafe {
+            match Self::try_store_in(data.as_ptr::<T>(metadata), &alloc) {
+                Ok((ptr, stack)) => {
+                    if !Data::<S>::inlined::<T>(metadata) {
+                        ManuallyDrop::into_inner(data.heap)
+                            .deallocate_layout(layout_from_metadata::<T>(metadata), &alloc);
+                    }
+
+                    Ok(Self {
+                        phantom: PhantomData,
+                        ptr,
+                        stack,
+                        alloc,
+                    })
+                }
+
+                Err(_) => Err(SmallBox(Inner::from_parts(data, metadata, alloc))),
+            }
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn from_small(boxed: SmallBox<T, S, A>) -> Self {
+        match Self::try_from_small(boxed) {
+            Ok(this) => this,
+            Err(boxed) => handle_alloc_error::<T>(metadata(&*boxed)),
+        }
+    }
+
+    #[inline]
+    pub fn try_into_small(boxed: Self) -> Result<SmallBox<T, S, A>, Self> {
+        let metadata = boxed.metadata();
+        let layout = layout_from_metadata::<T>(metadata);
+
+        let mut data = if Data::<S>::inlined::<T>(metadata) {
+            Data {
+                stack: ManuallyDrop::new(Stack::new_uninit()),
+            }
+        } else {
+            match Heap::try_allocate_in(layout, &boxed.alloc) {
+                Ok(heap) => Data {
+                    heap: ManuallyDrop::new(heap),
+                },
+                Err(_) => return Err(boxed),
+            }
+        };
+
+        let mut this = ManuallyDrop::new(boxed);
+
+        unsafe {
+            copy_nonoverlapping(
+                this.as_ptr() as *const u8,
+                data.as_mut_ptr::<T>(metadata) as *mut u8,
+                layout.size(),
+            );
+
+            this.deallocate();
+            Ok(SmallBox(Inner::from_parts(
+                data,
+                metadata,
+                read(&this.alloc),
+            )))
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn into_small(boxed: Self) -> SmallBox<T, S, A> {
+        match Self::try_into_small(boxed) {
+            Ok(boxed) => boxed,
+            Err(boxed) => handle_alloc_error::<T>(boxed.metadata()),
+        }
+    }
+
+    #[inline]
+    pub const fn inlined(metadata: <T as Pointee>::Metadata) -> bool {
+        let store = Layout::new::<S>();
+        let layout = layout_from_metadata::<T>(metadata);
+
+        size_of::<<T as Pointee>::Metadata>() <= store.size()
+            && layout.size() <= Self::TAIL
+            && layout.align() <= store.align()
+    }
+
+    #[inline]
+    pub fn is_inlined(boxed: &Self) -> bool {
+        boxed.is_inline()
+    }
+
+    #[inline]
+    pub fn allocator(boxed: &Self) -> &A {
+        &boxed.alloc
+    }
+
+    // heap blocks hold the metadata right in front of the value
+    #[inline]
+    fn heap_layout(metadata: <T as Pointee>::Metadata) -> Result<(Layout, usize), SmallBoxError> {
+        Ok(
+            Layout::new::<<T as Pointee>::Metadata>()
+                .extend(layout_from_metadata::<T>(metadata))?,
+        )
+    }
+
+    // copies the value behind `src` into new storage, the caller has to make
+    // sure it is not dropped afterwards
+    #[inline]
+    unsafe fn try_store_in(
+        src: *const T,
+        alloc: &A,
+    ) -> Result<(NonNull<u8>, Stack<S>), SmallBoxError> {
+        let metadata = metadata(src);
+        let size = layout_from_metadata::<T>(metadata).size();
+        let mut stack = Stack::new_uninit();
+
+        let ptr = if Self::inlined(metadata) {
+            let base = stack.as_mut_ptr::<u8>(());
+            write_unaligned(base.add(Self::TAIL) as *mut _, metadata);
+            copy_nonoverlapping(src as *const u8, base, size);
+            INLINE
+        } else {
+            let (layout, offset) = Self::heap_layout(metadata)?;
+            let block = alloc
+                .allocate(layout)
+                .map_err(|_| SmallBoxError::AllocError(layout))?;
+
+            let value = block.cast::<u8>().add(offset);
+            write_unaligned(
+                value.as_ptr().sub(size_of::<<T as Pointee>::Metadata>()) as *mut _,
+                metadata,
+            );
+            copy_nonoverlapping(src as *const u8, value.as_ptr(), size);
+            value
+        };
+
+        Ok((ptr, stack))
+    }
+
+    #[inline]
+    unsafe fn try_from_raw_in(src: *const T, alloc: A) -> Result<Self, SmallBoxError> {
+        let (ptr, stack) = Self::try_store_in(src, &alloc)?;
+
+        Ok(Self {
+            phantom: PhantomData,
+            ptr,
+            stack,
+            alloc,
+        })
+    }
+
+    #[inline]
+    fn is_inline(&self) -> bool {
+        self.ptr == INLINE
+    }
+
+    #[inline]
+    fn metadata(&self) -> <T as Pointee>::Metadata {
+        unsafe {
+            let header = if self.is_inline() {
+                self.stack.as_ptr::<u8>(()).add(Self::TAIL)
+            } else {
+                self.ptr.as_ptr().sub(size_of::<<T as Pointee>::Metadata>())
+            };
+
+            read_unaligned(header as *const _)
+        }
+    }
+
+    #[inline]
+    fn as_ptr(&self) -> *const T {
+        let metadata = self.metadata();
+
+        if self.is_inline() {
+            self.stack.as_ptr(metadata)
+        } else {
+            from_raw_parts(self.ptr.as_ptr() as *const (), metadata)
+        }
+    }
+
+    #[inline]
+    fn as_mut_ptr(&mut self) -> *mut T {
+        let metadata = self.metadata();
+
+        if self.is_inline() {
+            self.stack.as_mut_ptr(metadata)
+        } else {
+            from_raw_parts_mut(self.ptr.as_ptr() as *mut (), metadata)
+        }
+    }
+
+    // frees the heap block without dropping the value in it
+    #[inline]
+    unsafe fn deallocate(&mut self) {
+        if !self.is_inline() {
+            let (layout, offset) = Self::heap_layout(self.metadata()).unwrap_unchecked();
+            self.alloc.deallocate(self.ptr.sub(offset), layout);
+        }
+    }
+}
+
+unsafe impl<#[may_dangle] T: ?Sized, S, A: Allocator> Drop for ThinSmallBox<T, S, A> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            drop_in_place(self.as_mut_ptr());
+            self.deallocate();
+        }
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> Deref for ThinSmallBox<T, S, A> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> DerefMut for ThinSmallBox<T, S, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        unsafe { &mut *self.as_mut_ptr() }
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> AsRef<T> for ThinSmallBox<T, S, A> {
+    #[inline]
+    fn as_ref(&self) -> &T {
+        self
+    }
+}
+
+impl<T: ?Sized, S, A: Allocator> AsMut<T> for ThinSmallBox<T, S, A> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut T {
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<T: ?Sized, S, A: Allocator> From<SmallBox<T, S, A>> for ThinSmallBox<T, S, A> {
+    #[inline]
+    fn from(boxed: SmallBox<T, S, A>) -> Self {
+        Self::from_small(boxed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<T: ?Sized, S, A: Allocator> From<ThinSmallBox<T, S, A>> for SmallBox<T, S, A> {
+    #[inline]
+    fn from(boxed: ThinSmallBox<T, S, A>) -> Self {
+        ThinSmallBox::into_small(boxed)
+    }
+}
+
+impl<T: ?Sized + fmt::Display, S, A: Allocator> fmt::Display for ThinSmallBox<T, S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<T: ?Sized + fmt::Debug, S, A: Allocator> fmt::Debug for ThinSmallBox<T, S, A> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// same as for `SmallBox`, `S` never holds an actual `S`
+unsafe impl<T: ?Sized + Send, S, A: Allocator + Send> Send for ThinSmallBox<T, S, A> {}
+unsafe impl<T: ?Sized + Sync, S, A: Allocator + Sync> Sync for ThinSmallBox<T, S, A> {}
+
+impl<T: ?Sized, S, A: Allocator> Unpin for ThinSmallBox<T, S, A> {}
+
+impl<T: ?Sized + UnwindSafe, S, A: Allocator + UnwindSafe> UnwindSafe for ThinSmallBox<T, S, A> {}
+impl<T: ?Sized + RefUnwindSafe, S, A: Allocator + RefUnwindSafe> RefUnwindSafe
+    for ThinSmallBox<T, S, A>
+{
+}
@@ -0,0 +1,773 @@
+use core::{
+    alloc::{Allocator, Layout},
+    fmt,
+    hash::{Hash, Hasher},
+    iter::FusedIterator,
+    marker::PhantomData,
+    mem::{align_of, size_of, ManuallyDrop},
+    ops::{Bound, Deref, DerefMut, RangeBounds},
+    panic::{RefUnwindSafe, UnwindSafe},
+    ptr::{copy, copy_nonoverlapping, drop_in_place, read, slice_from_raw_parts_mut, write},
+    slice,
+};
+
+#[cfg(feature = "alloc")]
+use crate::inner::{handle_alloc_error, handle_error};
+use crate::{
+    inner::{Data, Heap, Inner, Stack},
+    SmallBox, SmallBoxError,
+};
+
+// smallest capacity a vector gets once it spills to the heap
+const MIN_HEAP_CAPACITY: usize = 4;
+
+#[cfg(feature = "alloc")]
+pub struct SmallBoxVec<T, Space, A: Allocator = alloc::alloc::Global> {
+    phantom: PhantomData<T>,
+    len: usize,
+    cap: usize,
+    data: Data<Space>,
+    alloc: A,
+}
+
+#[cfg(not(feature = "alloc"))]
+pub struct SmallBoxVec<T, Space, A: Allocator> {
+    phantom: PhantomData<T>,
+    len: usize,
+    cap: usize,
+    data: Data<Space>,
+    alloc: A,
+}
+
+impl<T, S, A: Allocator + Default> SmallBoxVec<T, S, A> {
+    #[inline]
+    pub fn new() -> Self {
+        Self::new_in(A::default())
+    }
+
+    #[inline]
+    pub fn try_with_capacity(capacity: usize) -> Result<Self, SmallBoxError> {
+        Self::try_with_capacity_in(capacity, A::default())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self::with_capacity_in(capacity, A::default())
+    }
+}
+
+impl<T, S, A: Allocator> SmallBoxVec<T, S, A> {
+    pub const INLINE_CAPACITY: usize = if size_of::<T>() == 0 {
+        usize::MAX
+    } else if align_of::<T>() > align_of::<S>() {
+        0
+    } else {
+        size_of::<S>() / size_of::<T>()
+    };
+
+    #[inline]
+    pub fn new_in(alloc: A) -> Self {
+        let data = if Data::<S>::inlined::<[T]>(Self::INLINE_CAPACITY) {
+            Data {
+                stack: ManuallyDrop::new(Stack::new_uninit()),
+            }
+        } else {
+            Data {
+                heap: ManuallyDrop::new(Heap::dangling::<T>()),
+            }
+        };
+
+        Self {
+            phantom: PhantomData,
+            len: 0,
+            cap: Self::INLINE_CAPACITY,
+            data,
+            alloc,
+        }
+    }
+
+    #[inline]
+    pub fn try_with_capacity_in(capacity: usize, alloc: A) -> Result<Self, SmallBoxError> {
+        let mut vec = Self::new_in(alloc);
+        vec.try_reserve_exact(capacity)?;
+        Ok(vec)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn with_capacity_in(capacity: usize, alloc: A) -> Self {
+        let mut vec = Self::new_in(alloc);
+        vec.reserve_exact(capacity);
+        vec
+    }
+
+    #[inline]
+    pub fn is_inlined(&self) -> bool {
+        Data::<S>::inlined::<[T]>(self.cap)
+    }
+
+    #[inline]
+    pub fn allocator(&self) -> &A {
+        &self.alloc
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    #[inline]
+    pub fn as_ptr(&self) -> *const T {
+        self.data.as_ptr::<[T]>(self.cap) as *const T
+    }
+
+    #[inline]
+    pub fn as_mut_ptr(&mut self) -> *mut T {
+        self.data.as_mut_ptr::<[T]>(self.cap) as *mut T
+    }
+
+    #[inline]
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.as_ptr(), self.len) }
+    }
+
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.as_mut_ptr(), self.len) }
+    }
+
+    /// # Safety
+    ///
+    /// `len` must not exceed the capacity, and the first `len` elements have to
+    /// be initialized.
+    #[inline]
+    pub unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    #[inline]
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), SmallBoxError> {
+        if self.cap - self.len >= additional {
+            return Ok(());
+        }
+
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(SmallBoxError::CapacityOverflow)?;
+        let capacity = required
+            .max(self.cap.saturating_mul(2))
+            .max(MIN_HEAP_CAPACITY);
+
+        self.try_grow_to(capacity)
+    }
+
+    #[inline]
+    pub fn try_reserve_exact(&mut self, additional: usize) -> Result<(), SmallBoxError> {
+        if self.cap - self.len >= additional {
+            return Ok(());
+        }
+
+        let required = self
+            .len
+            .checked_add(additional)
+            .ok_or(SmallBoxError::CapacityOverflow)?;
+        self.try_grow_to(required)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn reserve(&mut self, additional: usize) {
+        if let Err(err) = self.try_reserve(additional) {
+            handle_error(err)
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn reserve_exact(&mut self, additional: usize) {
+        if let Err(err) = self.try_reserve_exact(additional) {
+            handle_error(err)
+        }
+    }
+
+    #[inline]
+    pub fn try_shrink_to(&mut self, min_capacity: usize) -> Result<(), SmallBoxError> {
+        let capacity = self.len.max(min_capacity);
+
+        if size_of::<T>() == 0 || self.is_inlined() || capacity >= self.cap {
+            return Ok(());
+        }
+
+        // only heap storage can be larger than needed, the inline space
+        // is always exactly `INLINE_CAPACITY` elements
+        unsafe {
+            let layout = Layout::array::<T>(self.cap).unwrap_unchecked();
+
+            if Data::<S>::inlined::<[T]>(capacity) {
+                let heap = ManuallyDrop::take(&mut self.data.heap);
+                let mut stack = Stack::new_uninit();
+
+                copy_nonoverlapping(heap.as_ptr::<T>(()), stack.as_mut_ptr::<T>(()), self.len);
+
+                heap.deallocate_layout(layout, &self.alloc);
+                self.data = Data {
+                    stack: ManuallyDrop::new(stack),
+                };
+                self.cap = Self::INLINE_CAPACITY;
+            } else if capacity == 0 {
+                ManuallyDrop::take(&mut self.data.heap).deallocate_layout(layout, &self.alloc);
+                self.data = Data {
+                    heap: ManuallyDrop::new(Heap::dangling::<T>()),
+                };
+                self.cap = 0;
+            } else {
+                let new_layout = Layout::array::<T>(capacity).unwrap_unchecked();
+                (*self.data.heap)
+                    .try_shrink_in(layout, new_layout, &self.alloc)
+                    .map_err(|_| SmallBoxError::AllocError(new_layout))?;
+                self.cap = capacity;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn try_shrink_to_fit(&mut self) -> Result<(), SmallBoxError> {
+        self.try_shrink_to(0)
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn shrink_to(&mut self, min_capacity: usize) {
+        if let Err(err) = self.try_shrink_to(min_capacity) {
+            handle_error(err)
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn shrink_to_fit(&mut self) {
+        self.shrink_to(0)
+    }
+
+    #[inline]
+    pub fn push_within_capacity(&mut self, value: T) -> Result<(), T> {
+        if self.len == self.cap {
+            return Err(value);
+        }
+
+        unsafe { write(self.as_mut_ptr().add(self.len), value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+
+        unsafe { write(self.as_mut_ptr().add(self.len), value) };
+        self.len += 1;
+    }
+
+    #[inline]
+    pub fn try_extend_from_slice(&mut self, other: &[T]) -> Result<(), SmallBoxError>
+    where
+        T: Clone,
+    {
+        self.try_reserve(other.len())?;
+
+        for value in other {
+            unsafe { write(self.as_mut_ptr().add(self.len), value.clone()) };
+            self.len += 1;
+        }
+
+        Ok(())
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn extend_from_slice(&mut self, other: &[T])
+    where
+        T: Clone,
+    {
+        if let Err(err) = self.try_extend_from_slice(other) {
+            handle_error(err)
+        }
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            None
+        } else {
+            self.len -= 1;
+            Some(unsafe { read(self.as_ptr().add(self.len)) })
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn insert(&mut self, index: usize, value: T) {
+        let len = self.len;
+        assert!(
+            index <= len,
+            "insertion index (is {index}) should be <= len (is {len})"
+        );
+
+        if len == self.cap {
+            self.reserve(1);
+        }
+
+        unsafe {
+            let ptr = self.as_mut_ptr().add(index);
+            copy(ptr, ptr.add(1), len - index);
+            write(ptr, value);
+        }
+
+        self.len += 1;
+    }
+
+    #[inline]
+    pub fn remove(&mut self, index: usize) -> T {
+        let len = self.len;
+        assert!(
+            index < len,
+            "removal index (is {index}) should be < len (is {len})"
+        );
+
+        unsafe {
+            let ptr = self.as_mut_ptr().add(index);
+            let value = read(ptr);
+            copy(ptr.add(1), ptr, len - index - 1);
+            self.len -= 1;
+            value
+        }
+    }
+
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.len {
+            let tail =
+                slice_from_raw_parts_mut(unsafe { self.as_mut_ptr().add(len) }, self.len - len);
+            self.len = len;
+            unsafe { drop_in_place(tail) };
+        }
+    }
+
+    #[inline]
+    pub fn clear(&mut self) {
+        self.truncate(0)
+    }
+
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> Drain<'_, T, S, A> {
+        let len = self.len;
+
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start
+                .checked_add(1)
+                .expect("attempted to index slice from after maximum usize"),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&end) => end
+                .checked_add(1)
+                .expect("attempted to index slice up to maximum usize"),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => len,
+        };
+
+        assert!(
+            start <= end,
+            "slice index starts at {start} but ends at {end}"
+        );
+        assert!(
+            end <= len,
+            "range end index {end} out of range for slice of length {len}"
+        );
+
+        // elements in the drained range are logically moved out right away,
+        // the tail is moved back in place when `Drain` is dropped
+        self.len = start;
+
+        Drain {
+            vec: self,
+            front: start,
+            back: end,
+            tail_start: end,
+            tail_len: len - end,
+        }
+    }
+
+    pub fn try_into_boxed_slice(mut self) -> Result<SmallBox<[T], S, A>, Self> {
+        if self.try_shrink_to_fit().is_err() {
+            return Err(self);
+        }
+
+        // a `SmallBox` that does not fit inline always owns an allocation,
+        // even a zero-sized one
+        if !self.is_inlined()
+            && !Data::<S>::inlined::<[T]>(self.len)
+            && self.allocated_layout().is_none()
+        {
+            let layout = unsafe { Layout::array::<T>(self.len).unwrap_unchecked() };
+
+            match Heap::try_allocate_in(layout, &self.alloc) {
+                Ok(heap) => {
+                    self.data = Data {
+                        heap: ManuallyDrop::new(heap),
+                    }
+                }
+
+                Err(_) => return Err(self),
+            }
+        }
+
+        let this = ManuallyDrop::new(self);
+
+        unsafe {
+            let data = read(&this.data);
+            let alloc = read(&this.alloc);
+            Ok(SmallBox(Inner::from_parts(data, this.len, alloc)))
+        }
+    }
+
+    #[inline]
+    #[cfg(feature = "alloc")]
+    #[cfg(not(no_global_oom_handling))]
+    pub fn into_boxed_slice(self) -> SmallBox<[T], S, A> {
+        match self.try_into_boxed_slice() {
+            Ok(boxed) => boxed,
+            Err(vec) => handle_alloc_error::<[T]>(vec.len),
+        }
+    }
+
+    #[inline]
+    fn allocated_layout(&self) -> Option<Layout> {
+        if self.is_inlined() || size_of::<T>() == 0 || self.cap == 0 {
+            None
+        } else {
+            Some(unsafe { Layout::array::<T>(self.cap).unwrap_unchecked() })
+        }
+    }
+
+    #[cold]
+    fn try_grow_to(&mut self, capacity: usize) -> Result<(), SmallBoxError> {
+        let new_layout = Layout::array::<T>(capacity)?;
+        let alloc_error = |_| SmallBoxError::AllocError(new_layout);
+
+        unsafe {
+            if self.is_inlined() {
+                let mut heap =
+                    Heap::try_allocate_in(new_layout, &self.alloc).map_err(alloc_error)?;
+
+                copy_nonoverlapping(
+                    self.data.stack.as_ptr::<T>(()),
+                    heap.as_mut_ptr::<T>(()),
+                    self.len,
+                );
+
+                self.data = Data {
+                    heap: ManuallyDrop::new(heap),
+                };
+            } else if let Some(layout) = self.allocated_layout() {
+                (*self.data.heap)
+                    .try_grow_in(layout, new_layout, &self.alloc)
+                    .map_err(alloc_error)?;
+            } else {
+                self.data.heap = ManuallyDrop::new(
+                    Heap::try_allocate_in(new_layout, &self.alloc).map_err(alloc_error)?,
+                );
+            }
+        }
+
+        self.cap = capacity;
+        Ok(())
+    }
+}
+
+impl<T, S, A: Allocator> From<SmallBox<[T], S, A>> for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn from(boxed: SmallBox<[T], S, A>) -> Self {
+        let (mut data, len, alloc) = boxed.0.into_parts();
+
+        let cap = if Data::<S>::inlined::<[T]>(len) {
+            Self::INLINE_CAPACITY
+        } else {
+            let layout = unsafe { Layout::array::<T>(len).unwrap_unchecked() };
+
+            if layout.size() == 0 {
+                // the vector never holds zero-sized allocations
+                unsafe {
+                    ManuallyDrop::take(&mut data.heap).deallocate_layout(layout, &alloc);
+                }
+
+                data = Data {
+                    heap: ManuallyDrop::new(Heap::dangling::<T>()),
+                };
+
+                Self::INLINE_CAPACITY
+            } else {
+                len
+            }
+        };
+
+        Self {
+            phantom: PhantomData,
+            len,
+            cap,
+            data,
+            alloc,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<T, S, A: Allocator> From<SmallBoxVec<T, S, A>> for SmallBox<[T], S, A> {
+    #[inline]
+    fn from(vec: SmallBoxVec<T, S, A>) -> Self {
+        vec.into_boxed_slice()
+    }
+}
+
+impl<T, S, A: Allocator> SmallBox<[T], S, A> {
+    #[inline]
+    pub fn into_vec(self) -> SmallBoxVec<T, S, A> {
+        SmallBoxVec::from(self)
+    }
+}
+
+impl<T, S, A: Allocator> Drop for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn drop(&mut self) {
+        unsafe {
+            drop_in_place(self.as_mut_slice());
+
+            if let Some(layout) = self.allocated_layout() {
+                ManuallyDrop::take(&mut self.data.heap).deallocate_layout(layout, &self.alloc);
+            }
+        }
+    }
+}
+
+impl<T, S, A: Allocator> Deref for SmallBoxVec<T, S, A> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.as_slice()
+    }
+}
+
+impl<T, S, A: Allocator> DerefMut for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.as_mut_slice()
+    }
+}
+
+impl<T, S, A: Allocator> AsRef<[T]> for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn as_ref(&self) -> &[T] {
+        self
+    }
+}
+
+impl<T, S, A: Allocator> AsMut<[T]> for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn as_mut(&mut self) -> &mut [T] {
+        self
+    }
+}
+
+impl<T, S, A: Allocator + Default> Default for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<T, S, A: Allocator> Extend<T> for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+
+        for value in iter {
+            self.push(value);
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<'a, T: Copy + 'a, S, A: Allocator> Extend<&'a T> for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = &'a T>>(&mut self, iter: I) {
+        self.extend(iter.into_iter().copied())
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<T, S, A: Allocator + Default> FromIterator<T> for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        let mut vec = Self::new();
+        vec.extend(iter);
+        vec
+    }
+}
+
+#[cfg(feature = "alloc")]
+#[cfg(not(no_global_oom_handling))]
+impl<T: Clone, S, A: Allocator + Clone> Clone for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn clone(&self) -> Self {
+        let mut vec = Self::with_capacity_in(self.len, self.alloc.clone());
+        vec.extend(self.iter().cloned());
+        vec
+    }
+}
+
+impl<'a, T, S, A: Allocator> IntoIterator for &'a SmallBoxVec<T, S, A> {
+    type Item = &'a T;
+    type IntoIter = slice::Iter<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T, S, A: Allocator> IntoIterator for &'a mut SmallBoxVec<T, S, A> {
+    type Item = &'a mut T;
+    type IntoIter = slice::IterMut<'a, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T: fmt::Debug, S, A: Allocator> fmt::Debug for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<T: PartialEq, S, A: Allocator> PartialEq for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        PartialEq::eq(&**self, &**other)
+    }
+}
+
+impl<T: Eq, S, A: Allocator> Eq for SmallBoxVec<T, S, A> {}
+
+impl<T: Hash, S, A: Allocator> Hash for SmallBoxVec<T, S, A> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+unsafe impl<T: Send, S, A: Allocator + Send> Send for SmallBoxVec<T, S, A> {}
+unsafe impl<T: Sync, S, A: Allocator + Sync> Sync for SmallBoxVec<T, S, A> {}
+
+impl<T, S, A: Allocator> Unpin for SmallBoxVec<T, S, A> {}
+
+impl<T: UnwindSafe, S, A: Allocator + UnwindSafe> UnwindSafe for SmallBoxVec<T, S, A> {}
+impl<T: RefUnwindSafe, S, A: Allocator + RefUnwindSafe> RefUnwindSafe for SmallBoxVec<T, S, A> {}
+
+pub struct Drain<'a, T, S, A: Allocator> {
+    vec: &'a mut SmallBoxVec<T, S, A>,
+    front: usize,
+    back: usize,
+    tail_start: usize,
+    tail_len: usize,
+}
+
+impl<T, S, A: Allocator> Iterator for Drain<'_, T, S, A> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            let value = unsafe { read(self.vec.as_ptr().add(self.front)) };
+            self.front += 1;
+            Some(value)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<T, S, A: Allocator> DoubleEndedIterator for Drain<'_, T, S, A> {
+    #[inline]
+    fn next_back(&mut self) -> Option<T> {
+        if self.front == self.back {
+            None
+        } else {
+            self.back -= 1;
+            Some(unsafe { read(self.vec.as_ptr().add(self.back)) })
+        }
+    }
+}
+
+impl<T, S, A: Allocator> ExactSizeIterator for Drain<'_, T, S, A> {}
+
+impl<T, S, A: Allocator> FusedIterator for Drain<'_, T, S, A> {}
+
+impl<T, S, A: Allocator> Drop for Drain<'_, T, S, A> {
+    fn drop(&mut self) {
+        unsafe {
+            let ptr = self.vec.as_mut_ptr();
+            let rest = slice_from_raw_parts_mut(ptr.add(self.front), self.back - self.front);
+
+            // if one of the remaining elements panics on drop, the tail is leaked
+            self.front = self.back;
+            drop_in_place(rest);
+
+            let len = self.vec.len;
+            copy(ptr.add(self.tail_start), ptr.add(len), self.tail_len);
+            self.vec.len = len + self.tail_len;
+        }
+    }
+}
@@ -0,0 +1,92 @@
+extern crate smolbox;
+
+use assert_no_alloc::*;
+use smolbox::{BumpAllocator, SmallBox, SmallBoxVec};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_spill_and_reset() {
+    let mut bump = BumpAllocator::<256>::new();
+
+    assert_no_alloc(|| {
+        let small = SmallBox::<_, [usize; 1], _>::try_new_in(1u32, &bump).unwrap();
+        assert!(SmallBox::is_inlined(&small));
+        assert_eq!(bump.used(), 0);
+
+        let big = SmallBox::<_, [usize; 1], _>::try_new_in([7u64; 4], &bump).unwrap();
+        assert!(!SmallBox::is_inlined(&big));
+        assert_eq!(bump.used(), 32);
+
+        // deallocation is a no-op, the memory stays in use until the reset
+        drop(big);
+        assert_eq!(bump.used(), 32);
+
+        assert!(SmallBox::<_, [usize; 1], _>::try_new_in([0u8; 256], &bump).is_err());
+    });
+
+    bump.reset();
+    assert_eq!(bump.used(), 0);
+
+    let boxed = SmallBox::<_, [usize; 1], _>::try_new_in([0u8; 256], &bump).unwrap();
+    assert_eq!(*boxed, [0; 256]);
+}
+
+#[test]
+fn test_grow_in_place() {
+    let bump = BumpAllocator::<1024>::new();
+
+    let mut vec = SmallBoxVec::<u32, [u32; 0], _>::new_in(&bump);
+    vec.try_extend_from_slice(&[1, 2, 3, 4]).unwrap();
+    let ptr = vec.as_ptr();
+
+    // the vector holds the most recent allocation, so it grows in place
+    vec.try_reserve_exact(60).unwrap();
+    assert_eq!(vec.as_ptr(), ptr);
+    assert_eq!(bump.used(), 64 * 4);
+
+    vec.try_shrink_to_fit().unwrap();
+    assert_eq!(vec.as_ptr(), ptr);
+    assert_eq!(bump.used(), 4 * 4);
+    assert_eq!(&*vec, &[1, 2, 3, 4]);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_heap_box_conversions() {
+    let bump = BumpAllocator::<256>::new();
+
+    let boxed = SmallBox::<_, [usize; 0], _>::try_new_in(1usize, &bump).unwrap();
+    assert!(!SmallBox::is_inlined(&boxed));
+    let ptr = &*boxed as *const usize;
+
+    let mut boxed = SmallBox::try_into_box(boxed).unwrap();
+    assert_eq!(&*boxed as *const usize, ptr);
+    assert_eq!(*boxed, 1);
+    *boxed = 2;
+
+    let boxed: SmallBox<_, [usize; 0], _> = SmallBox::from_box(boxed);
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed as *const usize, ptr);
+    assert_eq!(*boxed, 2);
+    assert_eq!(bump.used(), 8);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_inlined_box_conversions() {
+    let bump = BumpAllocator::<256>::new();
+
+    let boxed = SmallBox::<_, [usize; 1], _>::try_new_in(1usize, &bump).unwrap();
+    assert!(SmallBox::is_inlined(&boxed));
+
+    let mut boxed = SmallBox::try_into_box(boxed).unwrap();
+    assert_eq!(bump.used(), 8);
+    *boxed = 2;
+
+    let boxed: SmallBox<_, [usize; 1], _> = SmallBox::from_box(boxed);
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(*boxed, 2);
+}
@@ -0,0 +1,225 @@
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{
+    alloc::Global,
+    cell::Cell,
+    panic::{catch_unwind, AssertUnwindSafe},
+    rc::Rc,
+};
+
+use assert_no_alloc::*;
+use smolbox::{HeaderSlice, SliceDst, SmallBox, SmallBoxError};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_header_and_slice() {
+    assert_no_alloc(|| {
+        let boxed =
+            SmallBox::<HeaderSlice<u16, [u8]>, [usize; 2], Global>::try_from_header_and_slice(
+                7,
+                &[1, 2, 3],
+            )
+            .unwrap();
+
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(boxed.header, 7);
+        assert_eq!(&boxed.slice, &[1, 2, 3]);
+    });
+
+    let boxed = SmallBox::<HeaderSlice<u16, [u8]>, [usize; 2], Global>::try_from_header_and_slice(
+        8, &[9; 32],
+    )
+    .unwrap();
+
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(boxed.header, 8);
+    assert_eq!(&boxed.slice, &[9; 32]);
+
+    let coerced: SmallBox<HeaderSlice<u16, [u8]>, [usize; 2], Global> = SmallBox::coerce(
+        SmallBox::try_new(HeaderSlice {
+            header: 7,
+            slice: [1, 2, 3],
+        })
+        .unwrap(),
+    );
+
+    assert!(SmallBox::is_inlined(&coerced));
+    assert_eq!(
+        SmallBox::<HeaderSlice<u16, [u8]>, [usize; 2], Global>::try_from_header_and_slice(
+            7,
+            &[1, 2, 3]
+        )
+        .unwrap(),
+        coerced
+    );
+}
+
+#[test]
+fn test_custom_dst() {
+    #[derive(Debug, PartialEq)]
+    struct Header {
+        kind: u8,
+        seq: u32,
+    }
+
+    struct Packet {
+        hdr: Header,
+        payload: [u8],
+    }
+
+    unsafe impl SliceDst for Packet {
+        type Header = Header;
+        type Item = u8;
+
+        unsafe fn header_ptr(this: *mut Self) -> *mut Header {
+            unsafe { &raw mut (*this).hdr }
+        }
+
+        unsafe fn slice_ptr(this: *mut Self) -> *mut [u8] {
+            unsafe { &raw mut (*this).payload }
+        }
+    }
+
+    let packet = SmallBox::<Packet, [usize; 4], Global>::try_from_header_and_iter(
+        Header { kind: 1, seq: 2 },
+        (0..16).map(|x| x * 2),
+    )
+    .unwrap();
+
+    assert!(SmallBox::is_inlined(&packet));
+    assert_eq!(packet.hdr, Header { kind: 1, seq: 2 });
+    assert_eq!(packet.payload.len(), 16);
+    assert!(packet
+        .payload
+        .iter()
+        .enumerate()
+        .all(|(i, &x)| x == i as u8 * 2));
+
+    let packet = SmallBox::<Packet, [usize; 4], Global>::try_from_header_and_slice(
+        Header { kind: 3, seq: 4 },
+        &[5; 64],
+    )
+    .unwrap();
+
+    assert!(!SmallBox::is_inlined(&packet));
+    assert_eq!(packet.hdr, Header { kind: 3, seq: 4 });
+    assert_eq!(&packet.payload, &[5; 64]);
+}
+
+#[test]
+fn test_header_and_iter_drop() {
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    type Counteds = HeaderSlice<Counted, [Counted]>;
+
+    let counter = Rc::new(Cell::new(0));
+    let items = |n| (0..n).map(|_| Counted(counter.clone())).collect::<Vec<_>>();
+
+    let inlined = SmallBox::<Counteds, [usize; 4], Global>::try_from_header_and_iter(
+        Counted(counter.clone()),
+        items(3),
+    )
+    .unwrap();
+    let heap = SmallBox::<Counteds, [usize; 4], Global>::try_from_header_and_iter(
+        Counted(counter.clone()),
+        items(8),
+    )
+    .unwrap();
+
+    assert!(SmallBox::is_inlined(&inlined));
+    assert!(!SmallBox::is_inlined(&heap));
+    assert_eq!(counter.get(), 0);
+
+    drop(inlined);
+    drop(heap);
+    assert_eq!(counter.get(), 13);
+
+    // the header and the items taken so far are dropped, the rest goes with the iterator
+    for len in [2, 8] {
+        counter.set(0);
+
+        let mut taken = 0;
+        let iter = items(len).into_iter().inspect(|_| {
+            taken += 1;
+            assert!(taken < len, "boom");
+        });
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            SmallBox::<Counteds, [usize; 4], Global>::try_from_header_and_iter(
+                Counted(counter.clone()),
+                iter,
+            )
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(counter.get(), len + 1);
+    }
+}
+
+#[test]
+#[should_panic(expected = "iterator yielded fewer items than its length")]
+fn test_header_and_iter_short() {
+    struct Liar(usize);
+
+    impl Iterator for Liar {
+        type Item = u8;
+
+        fn next(&mut self) -> Option<u8> {
+            None
+        }
+    }
+
+    impl ExactSizeIterator for Liar {
+        fn len(&self) -> usize {
+            self.0
+        }
+    }
+
+    let _ = SmallBox::<HeaderSlice<(), [u8]>, [usize; 2], Global>::try_from_header_and_iter(
+        (),
+        Liar(4),
+    );
+}
+
+#[test]
+fn test_header_and_slice_capacity_overflow() {
+    // zero-sized items take no space however many there are
+    let result = SmallBox::<HeaderSlice<u64, [()]>, [usize; 1], Global>::try_from_header_and_slice(
+        0,
+        &[(); 4],
+    );
+    assert!(SmallBox::is_inlined(&result.unwrap()));
+
+    struct Huge;
+
+    impl Iterator for Huge {
+        type Item = u64;
+
+        fn next(&mut self) -> Option<u64> {
+            unreachable!()
+        }
+    }
+
+    impl ExactSizeIterator for Huge {
+        fn len(&self) -> usize {
+            usize::MAX / 8
+        }
+    }
+
+    assert_eq!(
+        SmallBox::<HeaderSlice<u64, [u64]>, [usize; 1], Global>::try_from_header_and_iter(0, Huge)
+            .unwrap_err(),
+        SmallBoxError::CapacityOverflow
+    );
+}
@@ -0,0 +1,183 @@
+#![cfg(feature = "alloc")]
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{alloc::Global, cell::Cell, fmt::Debug, rc::Rc};
+
+use assert_no_alloc::*;
+use smolbox::{DynVec, SmallBox};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+trait Shape: Debug {
+    fn area(&self) -> u64;
+}
+
+#[derive(Debug, PartialEq)]
+struct Square(u8);
+
+#[derive(Debug, PartialEq)]
+#[repr(align(32))]
+struct Rect(u64, u64);
+
+#[derive(Debug, PartialEq)]
+struct Point;
+
+impl Shape for Square {
+    fn area(&self) -> u64 {
+        self.0 as u64 * self.0 as u64
+    }
+}
+
+impl Shape for Rect {
+    fn area(&self) -> u64 {
+        self.0 * self.1
+    }
+}
+
+impl Shape for Point {
+    fn area(&self) -> u64 {
+        0
+    }
+}
+
+fn shapes() -> DynVec<dyn Shape, Global> {
+    let mut vec = DynVec::new();
+    vec.push(Square(2));
+    vec.push(Rect(3, 4));
+    vec.push(Point);
+    vec.push(Square(5));
+    vec.push(Rect(6, 7));
+    vec
+}
+
+#[test]
+fn test_push_index() {
+    let mut vec = shapes();
+    assert_eq!(vec.len(), 5);
+    assert_eq!(vec[0].area(), 4);
+    assert_eq!(vec[1].area(), 12);
+    assert_eq!(vec[2].area(), 0);
+    assert_eq!(vec[3].area(), 25);
+    assert_eq!(vec[4].area(), 42);
+    assert!(vec.get(5).is_none());
+
+    assert_eq!(&vec[1] as *const dyn Shape as *const u8 as usize % 32, 0);
+    assert_eq!(&vec[4] as *const dyn Shape as *const u8 as usize % 32, 0);
+
+    for i in 0..100 {
+        vec.push(Square(i));
+    }
+
+    assert_eq!(vec.len(), 105);
+    assert_eq!(vec[1].area(), 12);
+    assert_eq!(vec[104].area(), 99 * 99);
+}
+
+#[test]
+fn test_iter() {
+    let mut vec = shapes();
+    assert!(vec.iter().map(|shape| shape.area()).eq([4, 12, 0, 25, 42]));
+    assert!(vec
+        .iter()
+        .rev()
+        .map(|shape| shape.area())
+        .eq([42, 25, 0, 12, 4]));
+    assert_eq!(vec.iter().len(), 5);
+
+    let mut vec_u32 = DynVec::<dyn Debug, Global>::new();
+    vec_u32.push(1u32);
+    vec_u32.push(2u32);
+
+    for value in &mut vec_u32 {
+        assert!(format!("{:?}", value).len() == 1);
+    }
+
+    vec.clear();
+    assert!(vec.is_empty());
+    assert_eq!(format!("{:?}", vec), "[]");
+}
+
+#[test]
+fn test_remove_pop() {
+    let mut vec = shapes();
+
+    let square = vec.remove::<[usize; 1]>(0);
+    assert!(SmallBox::is_inlined(&square));
+    assert_eq!(square.area(), 4);
+
+    // the following values are moved down, keeping their alignment
+    assert!(vec.iter().map(|shape| shape.area()).eq([12, 0, 25, 42]));
+    assert_eq!(&vec[0] as *const dyn Shape as *const u8 as usize % 32, 0);
+    assert_eq!(&vec[3] as *const dyn Shape as *const u8 as usize % 32, 0);
+
+    let rect = vec.pop::<[usize; 1]>().unwrap();
+    assert!(!SmallBox::is_inlined(&rect));
+    assert_eq!(rect.area(), 42);
+
+    let point = vec.remove::<[usize; 0]>(1);
+    assert_eq!(point.area(), 0);
+    assert!(vec.iter().map(|shape| shape.area()).eq([12, 25]));
+
+    vec.push(Point);
+    assert_eq!(format!("{:?}", vec), "[Rect(3, 4), Square(5), Point]");
+
+    assert!(vec.pop::<[usize; 0]>().is_some());
+    assert!(vec.pop::<[usize; 0]>().is_some());
+    assert!(vec.pop::<[usize; 0]>().is_some());
+    assert!(vec.pop::<[usize; 0]>().is_none());
+}
+
+#[test]
+#[should_panic]
+fn test_remove_out_of_bounds() {
+    let mut vec = shapes();
+    vec.remove::<[usize; 0]>(5);
+}
+
+#[test]
+fn test_drop() {
+    let counter = Rc::new(Cell::new(0));
+
+    #[derive(Debug)]
+    struct Counted(Rc<Cell<usize>>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    struct Big(Counted, [u64; 8]);
+
+    let mut vec = DynVec::<dyn Debug, Global>::new();
+    for i in 0..6 {
+        if i % 2 == 0 {
+            vec.push(Counted(counter.clone()));
+        } else {
+            vec.push(Big(Counted(counter.clone()), [0; 8]));
+        }
+    }
+
+    drop(vec.remove::<[usize; 1]>(1));
+    assert_eq!(counter.get(), 1);
+
+    drop(vec.pop::<[usize; 1]>());
+    assert_eq!(counter.get(), 2);
+
+    drop(vec);
+    assert_eq!(counter.get(), 6);
+}
+
+#[test]
+fn test_no_alloc_when_empty() {
+    assert_no_alloc(|| {
+        let vec = DynVec::<dyn Shape, Global>::new();
+        assert!(vec.is_empty());
+        assert!(vec.iter().next().is_none());
+    });
+}
@@ -0,0 +1,72 @@
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{alloc::Global, ptr::NonNull};
+
+use smolbox::{Fallback, Owns, PoolAllocator, SmallBox, SmallBoxVec};
+
+type Tiered<'a, const SIZE: usize> = Fallback<&'a PoolAllocator<SIZE>, Global>;
+
+#[test]
+fn test_placement() {
+    static POOL: PoolAllocator<512> = PoolAllocator::new();
+    let alloc: Tiered<'_, _> = Fallback::new(&POOL, Global);
+
+    let inline = SmallBox::<_, [usize; 1], _>::try_new_in(1u32, alloc).unwrap();
+    assert!(SmallBox::is_inlined(&inline));
+
+    let pooled = SmallBox::<_, [usize; 1], _>::try_new_in([1u64; 8], alloc).unwrap();
+    assert!(!SmallBox::is_inlined(&pooled));
+    assert!(POOL.owns(NonNull::from(&*pooled).cast()));
+
+    // too big for any size class of the pool
+    let global = SmallBox::<_, [usize; 1], _>::try_new_in([1u64; 1024], alloc).unwrap();
+    assert!(!POOL.owns(NonNull::from(&*global).cast()));
+
+    // blocks go back to the allocator they came from
+    let ptr = &*pooled as *const _ as usize;
+    drop(pooled);
+    drop(global);
+
+    let pooled = SmallBox::<_, [usize; 1], _>::try_new_in([2u64; 8], alloc).unwrap();
+    assert_eq!(&*pooled as *const _ as usize, ptr);
+}
+
+#[test]
+fn test_exhausted_primary() {
+    let pool = PoolAllocator::<128>::new();
+    let alloc: Tiered<'_, _> = Fallback::new(&pool, Global);
+
+    let boxes = (0..4)
+        .map(|i| SmallBox::<_, [usize; 0], _>::try_new_in([i as u8; 64], alloc).unwrap())
+        .collect::<Vec<_>>();
+
+    assert!(pool.owns(NonNull::from(&*boxes[1]).cast()));
+    assert!(!pool.owns(NonNull::from(&*boxes[2]).cast()));
+    assert!(!pool.owns(NonNull::from(&*boxes[3]).cast()));
+    assert!(boxes.iter().enumerate().all(|(i, b)| **b == [i as u8; 64]));
+}
+
+#[test]
+fn test_grow_across_tiers() {
+    static POOL: PoolAllocator<8192> = PoolAllocator::new();
+    let alloc: Tiered<'_, _> = Fallback::new(&POOL, Global);
+
+    let mut vec = SmallBoxVec::<u64, [u64; 2], _>::new_in(alloc);
+
+    for i in 0..8 {
+        vec.try_reserve(1).unwrap();
+        vec.push_within_capacity(i).unwrap();
+    }
+
+    assert!(POOL.owns(NonNull::new(vec.as_mut_ptr()).unwrap().cast()));
+
+    for i in 8..1000 {
+        vec.try_reserve(1).unwrap();
+        vec.push_within_capacity(i).unwrap();
+    }
+
+    assert!(!POOL.owns(NonNull::new(vec.as_mut_ptr()).unwrap().cast()));
+    assert!(vec.iter().copied().eq(0..1000));
+}
@@ -1,11 +1,22 @@
 #![feature(allocator_api)]
+#![allow(clippy::identity_op)]
 
 extern crate smolbox;
 
-use std::{alloc::Global, any::Any, mem::size_of};
+use std::{
+    alloc::{AllocError, Allocator, Global, Layout},
+    any::Any,
+    cell::Cell,
+    marker::{PhantomData, PhantomPinned},
+    mem::{size_of, ManuallyDrop, MaybeUninit},
+    panic::{RefUnwindSafe, UnwindSafe},
+    ptr::NonNull,
+    rc::Rc,
+};
 
 use assert_no_alloc::*;
-use smolbox::SmallBox;
+use impls::impls;
+use smolbox::{CloneFrom, SmallBox, SmallBoxError};
 
 #[cfg(not(miri))] // this fucks up miri for some reason, tests pass ok otherwise? seems like a miri issue
 #[global_allocator]
@@ -115,10 +126,243 @@ pub fn test_heap_any() {
     assert!(boxed.downcast::<u8>().is_err());
 }
 
+fn debug_len(boxed: SmallBox<dyn std::fmt::Debug, [usize; 2], Global>) -> usize {
+    format!("{:?}", boxed).len()
+}
+
+fn make_debug(value: u64) -> SmallBox<dyn std::fmt::Debug, [usize; 2], Global> {
+    SmallBox::coerce(SmallBox::try_new(value).unwrap())
+}
+
+#[test]
+fn test_coerce() {
+    let boxed: SmallBox<dyn Any, [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new(1usize).unwrap());
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(boxed.downcast_ref(), Some(&1usize));
+
+    let boxed: SmallBox<dyn Any, [u8; 0], Global> =
+        SmallBox::coerce(SmallBox::try_new(1usize).unwrap());
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(boxed.downcast_ref(), Some(&1usize));
+
+    // a zero-sized space aligned above 1
+    let boxed: SmallBox<dyn Any, [usize; 0], Global> =
+        SmallBox::coerce(SmallBox::try_new(1usize).unwrap());
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(boxed.downcast_ref(), Some(&1usize));
+
+    let boxed: SmallBox<dyn Any, [usize; 0], Global> =
+        SmallBox::coerce(SmallBox::try_new(()).unwrap());
+    assert!(SmallBox::is_inlined(&boxed));
+    assert!(boxed.is::<()>());
+
+    let slice: SmallBox<[u8], [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new([1u8, 2, 3]).unwrap());
+    assert!(SmallBox::is_inlined(&slice));
+    assert_eq!(&*slice, &[1, 2, 3]);
+
+    let slice: SmallBox<[u8], [usize; 0], Global> =
+        SmallBox::coerce(SmallBox::try_new([1u8, 2, 3]).unwrap());
+    assert!(!SmallBox::is_inlined(&slice));
+    assert_eq!(&*slice, &[1, 2, 3]);
+
+    assert_eq!(
+        debug_len(SmallBox::coerce(SmallBox::try_new([7u8; 3]).unwrap())),
+        9
+    );
+    assert_eq!(
+        debug_len(SmallBox::coerce(SmallBox::try_new([7u8; 64]).unwrap())),
+        64 * 3
+    );
+    assert_eq!(debug_len(make_debug(12)), 2);
+}
+
+#[test]
+fn test_downcast_send_sync() {
+    let mut boxed: SmallBox<dyn Any + Send + Sync, [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new(1usize).unwrap());
+    assert_eq!(boxed.downcast_ref::<usize>(), Some(&1));
+    *boxed.downcast_mut::<usize>().unwrap() = 2;
+
+    let boxed = boxed.downcast::<u8>().unwrap_err();
+    assert_eq!(*boxed.downcast::<usize>().unwrap(), 2);
+
+    let boxed: SmallBox<dyn Any + Send, [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new(3usize).unwrap());
+    assert!(boxed.is::<usize>());
+    assert_eq!(*unsafe { boxed.downcast_unchecked::<usize>() }, 3);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_downcast_into_box_resize() {
+    let boxed: SmallBox<dyn Any + Send, [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new(1usize).unwrap());
+    let boxed = boxed.downcast_into_box::<u8>().unwrap_err();
+    assert_eq!(*boxed.downcast_into_box::<usize>().unwrap(), 1);
+
+    let boxed: SmallBox<dyn Any, [u8; 0], Global> =
+        SmallBox::coerce(SmallBox::try_new([1usize, 2]).unwrap());
+    assert!(!SmallBox::is_inlined(&boxed));
+
+    let boxed = boxed
+        .downcast_resize::<[usize; 3], [usize; 2]>()
+        .unwrap_err();
+    let boxed = boxed.downcast_resize::<[usize; 2], [usize; 2]>().unwrap();
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(*boxed, [1, 2]);
+}
+
+trait Node: std::fmt::Debug + Any {
+    fn weight(&self) -> usize;
+}
+
+#[derive(Debug, PartialEq)]
+struct Leaf(usize);
+
+impl Node for Leaf {
+    fn weight(&self) -> usize {
+        self.0
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Branch([usize; 4]);
+
+impl Node for Branch {
+    fn weight(&self) -> usize {
+        self.0.iter().sum()
+    }
+}
+
+#[test]
+fn test_upcast() {
+    let leaf: SmallBox<dyn Node, [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new(Leaf(3)).unwrap());
+    assert!(SmallBox::is_inlined(&leaf));
+    assert_eq!(leaf.weight(), 3);
+
+    let leaf = SmallBox::coerce::<dyn Any>(leaf);
+    assert!(SmallBox::is_inlined(&leaf));
+    assert_eq!(leaf.downcast_ref(), Some(&Leaf(3)));
+
+    let branch: SmallBox<dyn Node, [usize; 1], Global> =
+        SmallBox::coerce(SmallBox::try_new(Branch([1, 2, 3, 4])).unwrap());
+    assert!(!SmallBox::is_inlined(&branch));
+    assert_eq!(branch.weight(), 10);
+
+    let ptr = &*branch as *const dyn Node as *const u8;
+    let branch = SmallBox::coerce::<dyn std::fmt::Debug>(branch);
+    assert_eq!(&*branch as *const dyn std::fmt::Debug as *const u8, ptr);
+    assert_eq!(format!("{:?}", branch), "Branch([1, 2, 3, 4])");
+}
+
+#[cfg(feature = "alloc")]
+static HANDLER: SmallBox<dyn Fn(u32) -> u32 + Sync, [usize; 1], Global> =
+    SmallBox::coerce(SmallBox::new_inline(|value: u32| value * 2));
+
+#[cfg(feature = "alloc")]
+static GREETING: SmallBox<str, [u8; 16], Global> = SmallBox::from_str_inline("hello");
+
+const PRIMES: SmallBox<[u32], [u32; 4], Global> = SmallBox::from_array_inline_in([2, 3, 5], Global);
+
+#[test]
+fn test_const_constructors() {
+    assert_no_alloc(|| {
+        #[cfg(feature = "alloc")]
+        {
+            assert!(SmallBox::is_inlined(&HANDLER));
+            assert_eq!(HANDLER(21), 42);
+
+            assert!(SmallBox::is_inlined(&GREETING));
+            assert_eq!(&*GREETING, "hello");
+        }
+
+        let mut primes = PRIMES;
+        assert!(SmallBox::is_inlined(&primes));
+        assert_eq!(&*primes, &[2, 3, 5]);
+
+        primes[0] = 7;
+        assert_eq!(&*primes, &[7, 3, 5]);
+        assert_eq!(&*PRIMES, &[2, 3, 5]);
+
+        // they work outside of const contexts just the same
+        let boxed = SmallBox::<_, [usize; 3], Global>::new_inline_in(String::new(), Global);
+        assert!(SmallBox::is_inlined(&boxed));
+        assert!(boxed.is_empty());
+    });
+}
+
+#[test]
+#[should_panic(expected = "does not fit inline")]
+fn test_from_str_inline_spills() {
+    SmallBox::<str, [u8; 4], Global>::from_str_inline_in("too long", Global);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_clone_str() {
+    let inline = SmallBox::<str, [u8; 8], Global>::try_new_clone_from_in("short", Global).unwrap();
+    let clone = inline.clone();
+    assert!(SmallBox::is_inlined(&clone));
+    assert_eq!(&*clone, "short");
+
+    let heap =
+        SmallBox::<str, [u8; 8], Global>::try_new_clone_from_in("much too long", Global).unwrap();
+    let clone = heap.clone();
+    assert!(!SmallBox::is_inlined(&clone));
+    assert_eq!(&*clone, "much too long");
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_clone_from_str_panicking_grow() {
+    #[derive(Clone)]
+    struct PanickingGrow;
+
+    unsafe impl Allocator for PanickingGrow {
+        fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+            Global.allocate(layout)
+        }
+
+        unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+            Global.deallocate(ptr, layout)
+        }
+
+        unsafe fn grow(
+            &self,
+            _: NonNull<u8>,
+            _: Layout,
+            _: Layout,
+        ) -> Result<NonNull<[u8]>, AllocError> {
+            panic!("grow")
+        }
+    }
+
+    let mut boxed =
+        SmallBox::<str, [u8; 4], _>::try_new_clone_from_in("spilled", PanickingGrow).unwrap();
+    let longer =
+        SmallBox::<str, [u8; 4], _>::try_new_clone_from_in("spilled further", PanickingGrow)
+            .unwrap();
+
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        boxed.clone_from(&longer);
+    }));
+
+    assert!(result.is_err());
+    assert_eq!(&*boxed, "spilled");
+
+    boxed.clone_from(&SmallBox::try_new_clone_from_in("abc", PanickingGrow).unwrap());
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, "abc");
+}
+
 #[test]
 pub fn test_drop() {
     use core::cell::Cell;
 
+    #[allow(dead_code)]
     struct Struct<'a>(&'a Cell<bool>, u8);
     impl<'a> Drop for Struct<'a> {
         fn drop(&mut self) {
@@ -144,6 +388,7 @@ pub fn test_drop() {
 #[test]
 fn test_zst() {
     #[derive(Debug, Eq, PartialEq)]
+    #[allow(clippy::upper_case_acronyms)]
     struct ZST;
 
     let zst: SmallBox<ZST, [usize; 0], Global> = SmallBox::try_new(ZST).unwrap();
@@ -166,6 +411,27 @@ fn test_sizes() {
     assert!(size_of::<SmallBox<[u8], [usize; 3], Global>>() == 4 * ptr);
 }
 
+#[test]
+fn test_option_sizes() {
+    // the vtable pointer of trait objects is never null, so `None` costs nothing
+    fn same_size<T: ?Sized, S>() -> bool {
+        size_of::<Option<SmallBox<T, S, Global>>>() == size_of::<SmallBox<T, S, Global>>()
+    }
+
+    assert!(same_size::<dyn Any, [usize; 0]>());
+    assert!(same_size::<dyn Any, [usize; 4]>());
+
+    let none: Option<SmallBox<dyn Any, [usize; 2], Global>> = None;
+    assert!(none.is_none());
+
+    let some: Option<SmallBox<dyn Any, [usize; 2], Global>> =
+        Some(SmallBox::coerce(SmallBox::try_new(1usize).unwrap()));
+    assert_eq!(
+        some.as_deref().and_then(<dyn Any>::downcast_ref::<usize>),
+        Some(&1)
+    );
+}
+
 #[test]
 fn test_resize_stack() {
     let boxed = SmallBox::<_, [usize; 0], Global>::try_new(1usize).unwrap();
@@ -218,3 +484,349 @@ fn test_inlined_box_conversions() {
     assert!(SmallBox::is_inlined(&boxed));
     assert_eq!(*boxed, 2);
 }
+
+#[test]
+fn test_slice_capacity_overflow() {
+    assert_no_alloc(|| {
+        let len = usize::MAX / 2;
+
+        let boxed = SmallBox::<[MaybeUninit<u64>], [usize; 1], Global>::try_new_uninit_slice(len);
+        assert_eq!(boxed.err(), Some(SmallBoxError::CapacityOverflow));
+
+        let boxed = SmallBox::<[MaybeUninit<u64>], [usize; 1], Global>::try_new_zeroed_slice(len);
+        assert_eq!(boxed.err(), Some(SmallBoxError::CapacityOverflow));
+
+        // zero-sized elements never overflow
+        let boxed = SmallBox::<[MaybeUninit<()>], [usize; 0], Global>::try_new_uninit_slice(len);
+        assert_eq!(boxed.unwrap().len(), len);
+    });
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+#[should_panic(expected = "capacity overflow")]
+fn test_slice_capacity_overflow_panics() {
+    SmallBox::<[MaybeUninit<u64>], [usize; 1], Global>::new_uninit_slice(usize::MAX / 2);
+}
+
+#[test]
+fn test_truncate() {
+    assert_no_alloc(|| {
+        let mut boxed = SmallBox::<[u32], [u32; 4], Global>::try_copy_from(&[1, 2, 3]).unwrap();
+        SmallBox::try_truncate(&mut boxed, 1).unwrap();
+
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[1]);
+    });
+
+    let mut boxed = SmallBox::<[u32], [u32; 2], Global>::try_copy_from(&[1, 2, 3, 4, 5]).unwrap();
+    SmallBox::try_truncate(&mut boxed, 3).unwrap();
+
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2, 3]);
+
+    // a heap slice that fits again moves back into the inline space
+    SmallBox::try_truncate(&mut boxed, 2).unwrap();
+
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2]);
+
+    SmallBox::try_truncate(&mut boxed, 4).unwrap();
+    assert_eq!(&*boxed, &[1, 2]);
+}
+
+#[test]
+fn test_truncate_drop() {
+    let counter = Rc::new(Cell::new(0));
+
+    #[derive(Clone)]
+    struct Counted(Rc<Cell<usize>>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let mut boxed = SmallBox::<[_], [usize; 2], Global>::try_new_uninit_slice(6).unwrap();
+    for slot in boxed.iter_mut() {
+        slot.write(Counted(counter.clone()));
+    }
+    let mut boxed = unsafe { boxed.assume_init() };
+
+    SmallBox::try_truncate(&mut boxed, 4).unwrap();
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(counter.get(), 2);
+
+    SmallBox::try_truncate(&mut boxed, 1).unwrap();
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(counter.get(), 5);
+
+    SmallBox::try_grow(&mut boxed, 3, Counted(counter.clone())).unwrap();
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(counter.get(), 6);
+
+    drop(boxed);
+    assert_eq!(counter.get(), 9);
+}
+
+#[test]
+fn test_grow() {
+    assert_no_alloc(|| {
+        let mut boxed = SmallBox::<[u32], [u32; 4], Global>::try_copy_from(&[1]).unwrap();
+        SmallBox::try_grow(&mut boxed, 3, 7).unwrap();
+
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[1, 7, 7]);
+    });
+
+    let mut boxed = SmallBox::<[u32], [u32; 2], Global>::try_copy_from(&[1, 2]).unwrap();
+    SmallBox::try_grow(&mut boxed, 3, 3).unwrap();
+
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2, 3]);
+
+    // a shorter length leaves the box as it is
+    SmallBox::try_grow(&mut boxed, 1, 9).unwrap();
+    assert_eq!(&*boxed, &[1, 2, 3]);
+
+    SmallBox::try_grow(&mut boxed, 100, 4).unwrap();
+
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&boxed[..3], &[1, 2, 3]);
+    assert!(boxed[3..].iter().all(|&x| x == 4));
+
+    assert_eq!(
+        SmallBox::try_grow(&mut boxed, usize::MAX, 0),
+        Err(SmallBoxError::CapacityOverflow)
+    );
+    assert_eq!(boxed.len(), 100);
+}
+
+#[test]
+fn test_unsized_move() {
+    let counter = Rc::new(Cell::new(0));
+
+    struct Counted(Rc<Cell<usize>>, [usize; 2]);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let mut value = ManuallyDrop::new(Counted(counter.clone(), [1, 2]));
+    let src: *mut dyn Any = &mut *value as *mut Counted;
+    let inlined =
+        unsafe { SmallBox::<dyn Any, [usize; 3], Global>::try_from_unsized_move(src) }.unwrap();
+    assert!(SmallBox::is_inlined(&inlined));
+
+    let mut value = ManuallyDrop::new(Counted(counter.clone(), [3, 4]));
+    let src: *mut dyn Any = &mut *value as *mut Counted;
+    let heap =
+        unsafe { SmallBox::<dyn Any, [usize; 2], Global>::try_from_unsized_move(src) }.unwrap();
+    assert!(!SmallBox::is_inlined(&heap));
+
+    assert_eq!(heap.downcast_ref::<Counted>().unwrap().1, [3, 4]);
+    assert_eq!(counter.get(), 0);
+
+    fn move_out<S>(boxed: SmallBox<dyn Any, S, Global>, counter: &Cell<usize>) {
+        let before = counter.get();
+        let mut dst = MaybeUninit::<[usize; 3]>::uninit();
+        let moved = unsafe { SmallBox::into_unsized_move(boxed, dst.as_mut_ptr().cast()) };
+
+        assert_eq!(moved as *mut u8, dst.as_mut_ptr().cast());
+        assert!(unsafe { (*moved).is::<Counted>() });
+        assert_eq!(counter.get(), before);
+
+        unsafe { moved.drop_in_place() };
+        assert_eq!(counter.get(), before + 1);
+    }
+
+    move_out(inlined, &counter);
+    move_out(heap, &counter);
+}
+
+#[test]
+fn test_slice_builders() {
+    assert_no_alloc(|| {
+        let boxed = SmallBox::<[usize], [usize; 4], Global>::try_from_fn(4, |i| i * i).unwrap();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[0, 1, 4, 9]);
+
+        let boxed = SmallBox::<[u8], [usize; 1], Global>::try_repeat(7, 8).unwrap();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[7; 8]);
+
+        let boxed = SmallBox::<[u16], [usize; 1], Global>::try_from_array([1, 2, 3]).unwrap();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[1, 2, 3]);
+    });
+
+    let boxed = SmallBox::<[usize], [usize; 4], Global>::try_from_fn(5, |i| i).unwrap();
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[0, 1, 2, 3, 4]);
+
+    let boxed =
+        SmallBox::<[u32], [usize; 1], Global>::try_init_with(3, |i, slot| slot.write(i as u32 + 1))
+            .unwrap();
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2, 3]);
+
+    // the last element takes the value itself
+    let value = Rc::new(());
+    let boxed = SmallBox::<[Rc<()>], [usize; 4], Global>::try_repeat(value.clone(), 3).unwrap();
+    assert_eq!(Rc::strong_count(&value), 4);
+    drop(boxed);
+    assert_eq!(Rc::strong_count(&value), 1);
+
+    let empty = SmallBox::<[Rc<()>], [usize; 4], Global>::try_repeat(value.clone(), 0).unwrap();
+    assert!(empty.is_empty());
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn test_from_fn_panic() {
+    let counter = Rc::new(Cell::new(0));
+
+    struct Counted(Rc<Cell<usize>>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    for len in [2, 6] {
+        counter.set(0);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            SmallBox::<[Counted], [usize; 4], Global>::try_from_fn(len, |i| {
+                assert!(i + 1 < len, "boom");
+                Counted(counter.clone())
+            })
+        }));
+
+        // only the elements made before the panic get dropped
+        assert!(result.is_err());
+        assert_eq!(counter.get(), len - 1);
+
+        counter.set(0);
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            SmallBox::<[Counted], [usize; 4], Global>::try_init_with(len, |i, slot| {
+                assert!(i + 1 < len, "boom");
+                slot.write(Counted(counter.clone()))
+            })
+        }));
+
+        assert!(result.is_err());
+        assert_eq!(counter.get(), len - 1);
+    }
+}
+
+#[test]
+#[should_panic(expected = "has to return the slot it was given")]
+fn test_init_with_wrong_slot() {
+    let mut other = Some(Box::leak(Box::new(0u32)));
+    let _ = SmallBox::<[u32], [usize; 1], Global>::try_init_with(1, |_, _| other.take().unwrap());
+}
+
+#[test]
+fn test_try_from_slice() {
+    assert_no_alloc(|| {
+        let boxed = SmallBox::<[u8], [usize; 1], Global>::try_copy_from(&[1, 2, 3]).unwrap();
+        let array = SmallBox::<[u8; 3], _, _>::try_from(boxed).unwrap();
+
+        assert!(SmallBox::is_inlined(&array));
+        assert_eq!(*array, [1, 2, 3]);
+    });
+
+    let boxed = SmallBox::<[u8], [usize; 1], Global>::try_copy_from(&[7; 32]).unwrap();
+    let addr = boxed.as_ptr();
+
+    // a length mismatch hands the box back untouched
+    let boxed = SmallBox::<[u8; 31], _, _>::try_from(boxed).unwrap_err();
+    assert_eq!(boxed.as_ptr(), addr);
+
+    let array = SmallBox::<[u8; 32], _, _>::try_from(boxed).unwrap();
+    assert!(!SmallBox::is_inlined(&array));
+    assert_eq!(array.as_ptr(), addr);
+    assert_eq!(*array, [7; 32]);
+}
+
+// allocator that is neither `Send` nor `Sync`, like an `Rc`-backed arena handle
+#[derive(Default)]
+struct LocalAlloc(PhantomData<Rc<()>>);
+
+unsafe impl Allocator for LocalAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Global.deallocate(ptr, layout)
+    }
+}
+
+#[test]
+fn test_may_dangle() {
+    // the box outlives `value`, which compiles only because dropping the box
+    // does not touch the reference, same as with `Box`
+    let mut boxed = SmallBox::<Option<&String>, [usize; 1], Global>::try_new(None).unwrap();
+    let value = String::from("value");
+    *boxed = Some(&value);
+    assert_eq!(boxed.as_deref().map(String::as_str), Some("value"));
+
+    let mut heap =
+        SmallBox::<[Option<&String>], [usize; 0], Global>::try_new_clone_from_in(&[None], Global)
+            .unwrap();
+    let value = String::from("value");
+    heap[0] = Some(&value);
+    assert!(!SmallBox::is_inlined(&heap));
+}
+
+// allocator that is neither `UnwindSafe` nor `RefUnwindSafe`
+#[derive(Default)]
+struct CellAlloc(PhantomData<Cell<()>>, PhantomData<&'static mut ()>);
+
+unsafe impl Allocator for CellAlloc {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        Global.deallocate(ptr, layout)
+    }
+}
+
+#[test]
+fn test_auto_traits_send_sync() {
+    assert!(impls!(SmallBox<u8, [usize; 1], Global>: Send & Sync));
+    assert!(impls!(SmallBox<dyn Any + Send + Sync, [usize; 1], Global>: Send & Sync));
+    assert!(impls!(SmallBox<Cell<u8>, [usize; 1], Global>: Send & !Sync));
+    assert!(impls!(SmallBox<Rc<u8>, [usize; 1], Global>: !Send & !Sync));
+
+    // the inline space never holds an actual `S`
+    assert!(impls!(SmallBox<u8, [*const u8; 1], Global>: Send & Sync));
+
+    // the allocator is carried along with the value
+    assert!(impls!(SmallBox<u8, [usize; 1], LocalAlloc>: !Send & !Sync));
+    assert!(impls!(Box<u8, LocalAlloc>: !Send & !Sync));
+}
+
+#[test]
+fn test_auto_traits_unpin() {
+    assert!(impls!(SmallBox<PhantomPinned, [usize; 1], Global>: Unpin));
+    assert!(impls!(SmallBox<PhantomPinned, [usize; 0], Global>: Unpin));
+    assert!(impls!(SmallBox<u8, [PhantomPinned; 1], Global>: Unpin));
+    assert!(impls!(Box<PhantomPinned>: Unpin));
+}
+
+#[test]
+fn test_auto_traits_unwind_safe() {
+    assert!(impls!(SmallBox<u8, [usize; 1], Global>: UnwindSafe & RefUnwindSafe));
+    assert!(impls!(SmallBox<Cell<u8>, [usize; 1], Global>: UnwindSafe & !RefUnwindSafe));
+    assert!(impls!(SmallBox<&mut u8, [usize; 1], Global>: !UnwindSafe));
+    assert!(impls!(SmallBox<u8, [&mut u8; 1], Global>: UnwindSafe & RefUnwindSafe));
+
+    assert!(impls!(SmallBox<u8, [usize; 1], CellAlloc>: !UnwindSafe & !RefUnwindSafe));
+    assert!(impls!(Box<u8, CellAlloc>: !UnwindSafe & !RefUnwindSafe));
+}
@@ -0,0 +1,94 @@
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{
+    alloc::{Allocator, Layout},
+    ptr::NonNull,
+};
+
+use assert_no_alloc::*;
+use smolbox::{Owns, PoolAllocator, SmallBox, SmallBoxVec};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_static_pool() {
+    static POOL: PoolAllocator<1024> = PoolAllocator::new();
+
+    assert_no_alloc(|| {
+        let boxed = SmallBox::<_, [usize; 1], _>::try_new_in([1u64; 4], &POOL).unwrap();
+        assert!(!SmallBox::is_inlined(&boxed));
+        assert!(POOL.owns(NonNull::from(&*boxed).cast()));
+        assert_eq!(*boxed, [1; 4]);
+
+        // freed blocks are reused by the next spill of the same size class
+        let ptr = &*boxed as *const _ as usize;
+        drop(boxed);
+
+        let boxed = SmallBox::<_, [usize; 1], _>::try_new_in([2u64; 3], &POOL).unwrap();
+        assert_eq!(&*boxed as *const _ as usize, ptr);
+    });
+}
+
+#[test]
+fn test_return_from_fn() {
+    static POOL: PoolAllocator<1024> = PoolAllocator::new();
+
+    fn make(value: u64) -> SmallBox<[u64; 4], [usize; 1], &'static PoolAllocator<1024>> {
+        SmallBox::try_new_in([value; 4], &POOL).unwrap()
+    }
+
+    let boxes = (0..4).map(make).collect::<Vec<_>>();
+
+    assert!(boxes.iter().all(|b| POOL.owns(NonNull::from(&**b).cast())));
+    assert!(boxes.iter().enumerate().all(|(i, b)| **b == [i as u64; 4]));
+}
+
+#[test]
+fn test_exhaustion() {
+    let pool = PoolAllocator::<256>::new();
+
+    let boxes = (0..4)
+        .map(|i| SmallBox::<_, [usize; 0], _>::try_new_in([i as u8; 64], &pool).unwrap())
+        .collect::<Vec<_>>();
+
+    assert!(SmallBox::<_, [usize; 0], _>::try_new_in([0u8; 64], &pool).is_err());
+    assert!(SmallBox::<_, [usize; 0], _>::try_new_in(0u8, &pool).is_err());
+
+    drop(boxes);
+    assert!(SmallBox::<_, [usize; 0], _>::try_new_in([0u8; 64], &pool).is_ok());
+}
+
+#[test]
+fn test_unsupported_layouts() {
+    let pool = PoolAllocator::<{ 16 * 1024 }>::new();
+
+    let layout = Layout::from_size_align(PoolAllocator::<0>::MAX_BLOCK_SIZE + 1, 1).unwrap();
+    assert!((&pool).allocate(layout).is_err());
+
+    let layout = Layout::from_size_align(8, 128).unwrap();
+    assert!((&pool).allocate(layout).is_err());
+
+    let layout = Layout::from_size_align(8, 64).unwrap();
+    let block = (&pool).allocate(layout).unwrap();
+    assert_eq!(block.as_ptr() as *mut u8 as usize % 64, 0);
+    assert_eq!(block.len(), 64);
+}
+
+#[test]
+fn test_grow_within_class() {
+    let pool = PoolAllocator::<1024>::new();
+
+    let mut vec = SmallBoxVec::<u8, [u8; 0], _>::try_with_capacity_in(17, &pool).unwrap();
+    let ptr = vec.as_ptr();
+
+    // 17..=32 bytes all map to the 32 byte class, so the block stays put
+    vec.try_reserve_exact(32).unwrap();
+    assert_eq!(vec.as_ptr(), ptr);
+
+    vec.try_reserve_exact(33).unwrap();
+    assert_ne!(vec.as_ptr(), ptr);
+}
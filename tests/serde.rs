@@ -0,0 +1,119 @@
+#![cfg(feature = "serde")]
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::alloc::Global;
+
+use assert_no_alloc::*;
+use serde::{
+    de::value::{self, BorrowedStrDeserializer, SeqDeserializer},
+    Deserialize,
+};
+use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_tokens, Token};
+use smolbox::SmallBox;
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_sized() {
+    let boxed = SmallBox::<_, [usize; 1], Global>::try_new(42u32).unwrap();
+    assert_tokens(&boxed, &[Token::U32(42)]);
+
+    let boxed = SmallBox::<_, [usize; 0], Global>::try_new(42u32).unwrap();
+    assert_tokens(&boxed, &[Token::U32(42)]);
+}
+
+#[test]
+fn test_str() {
+    let boxed = SmallBox::<str, [usize; 2], Global>::try_copy_from("hello").unwrap();
+    assert_tokens(&boxed, &[Token::Str("hello")]);
+    assert_de_tokens(&boxed, &[Token::String("hello")]);
+    assert_de_tokens(&boxed, &[Token::Bytes(b"hello")]);
+
+    let boxed = SmallBox::<str, [usize; 0], Global>::try_copy_from("hello").unwrap();
+    assert_tokens(&boxed, &[Token::Str("hello")]);
+
+    assert_de_tokens_error::<SmallBox<str, [usize; 2], Global>>(
+        &[Token::Bytes(b"\xff")],
+        "invalid value: byte array, expected a string",
+    );
+}
+
+#[test]
+fn test_slice() {
+    let tokens = [
+        Token::Seq { len: Some(3) },
+        Token::U32(1),
+        Token::U32(2),
+        Token::U32(3),
+        Token::SeqEnd,
+    ];
+
+    let boxed = SmallBox::<[u32], [u32; 3], Global>::try_copy_from(&[1, 2, 3]).unwrap();
+    assert_tokens(&boxed, &tokens);
+
+    let boxed = SmallBox::<[u32], [u32; 1], Global>::try_copy_from(&[1, 2, 3]).unwrap();
+    assert_tokens(&boxed, &tokens);
+
+    let boxed = SmallBox::<[u32], [u32; 8], Global>::try_copy_from(&[1, 2, 3]).unwrap();
+    assert_tokens(&boxed, &tokens);
+
+    let boxed = SmallBox::<[u32], [u32; 0], Global>::try_copy_from(&[]).unwrap();
+    assert_tokens(&boxed, &[Token::Seq { len: Some(0) }, Token::SeqEnd]);
+}
+
+#[test]
+fn test_slice_unknown_len() {
+    let tokens = (0..100).map(Token::U32);
+    let tokens = [Token::Seq { len: None }]
+        .into_iter()
+        .chain(tokens)
+        .chain([Token::SeqEnd])
+        .collect::<Vec<_>>();
+
+    let expected = (0..100).collect::<Vec<u32>>();
+    let boxed = SmallBox::<[u32], [u32; 2], Global>::try_copy_from(&expected).unwrap();
+    assert_de_tokens(&boxed, &tokens);
+}
+
+#[test]
+fn test_slice_of_boxes() {
+    let strings = [
+        SmallBox::<str, [usize; 1], Global>::try_copy_from("a").unwrap(),
+        SmallBox::<str, [usize; 1], Global>::try_copy_from("a long string that spills").unwrap(),
+    ];
+
+    let mut slice = SmallBox::<[_], [usize; 0], Global>::try_new_uninit_slice(2).unwrap();
+    for (slot, string) in slice.iter_mut().zip(strings) {
+        slot.write(string);
+    }
+    let slice = unsafe { slice.assume_init() };
+
+    assert_tokens(
+        &slice,
+        &[
+            Token::Seq { len: Some(2) },
+            Token::Str("a"),
+            Token::Str("a long string that spills"),
+            Token::SeqEnd,
+        ],
+    );
+}
+
+#[test]
+fn test_inline_without_alloc() {
+    assert_no_alloc(|| {
+        let de = BorrowedStrDeserializer::<value::Error>::new("short");
+        let boxed = SmallBox::<str, [usize; 1], Global>::deserialize(de).unwrap();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, "short");
+
+        let de = SeqDeserializer::<_, value::Error>::new([1u8, 2, 3].into_iter());
+        let boxed = SmallBox::<[u8], [u8; 4], Global>::deserialize(de).unwrap();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[1, 2, 3]);
+    });
+}
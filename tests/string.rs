@@ -0,0 +1,109 @@
+#![cfg(feature = "alloc")]
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{alloc::Global, fmt::Write};
+
+use assert_no_alloc::*;
+use smolbox::{SmallBox, SmallString};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_inlined_push() {
+    assert_no_alloc(|| {
+        let mut string = SmallString::<[usize; 2], Global>::new();
+        assert!(string.is_inlined());
+
+        string.push_str("hello");
+        string.push(' ');
+        string.push('ф');
+        string.extend(['!', '?']);
+
+        assert!(string.is_inlined());
+        assert_eq!(string, "hello ф!?");
+        assert_eq!(string.len(), 10);
+    });
+}
+
+#[test]
+fn test_spill() {
+    let mut string = SmallString::<[u8; 4], Global>::new();
+    string.push_str("abcd");
+    assert!(string.is_inlined());
+
+    string.push('e');
+    assert!(!string.is_inlined());
+    assert_eq!(string, "abcde");
+
+    string.truncate(2);
+    string.shrink_to_fit();
+    assert!(string.is_inlined());
+    assert_eq!(string, "ab");
+}
+
+#[test]
+fn test_insert_remove_pop() {
+    let mut string = SmallString::<[u8; 16], Global>::from("hllo");
+    string.insert(1, 'e');
+    string.insert_str(0, "¡");
+    string.insert_str(string.len(), " world");
+    assert_eq!(string, "¡hello world");
+
+    assert_eq!(string.remove(0), '¡');
+    assert_eq!(string.pop(), Some('d'));
+    assert_eq!(string, "hello worl");
+
+    string.clear();
+    assert_eq!(string.pop(), None);
+}
+
+#[test]
+#[should_panic]
+fn test_insert_not_char_boundary() {
+    let mut string = SmallString::<[u8; 16], Global>::from("ф");
+    string.insert(1, 'a');
+}
+
+#[test]
+#[should_panic]
+fn test_truncate_not_char_boundary() {
+    let mut string = SmallString::<[u8; 16], Global>::from("ф");
+    string.truncate(1);
+}
+
+#[test]
+fn test_fmt_write() {
+    let mut string = SmallString::<[usize; 4], Global>::new();
+    write!(string, "{:?}-{:03}", 'x', 7).unwrap();
+    assert_eq!(string, "'x'-007");
+    assert_eq!(format!("{string:?}"), "\"'x'-007\"");
+}
+
+#[test]
+fn test_box_conversions() {
+    assert_no_alloc(|| {
+        let boxed = SmallBox::<str, [usize; 2], Global>::try_copy_from("abc").unwrap();
+        let mut string = boxed.into_string();
+        assert!(string.is_inlined());
+
+        string.push_str("def");
+
+        let boxed = string.into_boxed_str();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, "abcdef");
+    });
+
+    let boxed = SmallBox::<str, [usize; 0], Global>::copy_from("abc");
+    let mut string = SmallString::from(boxed);
+    assert!(!string.is_inlined());
+
+    string.push_str("def");
+
+    let boxed: SmallBox<str, [usize; 0], Global> = string.into();
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, "abcdef");
+}
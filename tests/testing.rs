@@ -0,0 +1,320 @@
+#![cfg(feature = "testing")]
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{
+    alloc::{Allocator, Layout},
+    any::Any,
+    mem::{forget, MaybeUninit},
+};
+
+use smolbox::{
+    testing::{FailingAllocator, TrackingAllocator},
+    CloneFrom, CopyFrom, DynVec, SmallBox, SmallBoxError, SmallBoxVec, SmallString,
+};
+
+type Alloc = TrackingAllocator<FailingAllocator>;
+
+fn fail_nth(n: usize) -> Alloc {
+    TrackingAllocator::new_in(FailingAllocator::fail_nth(n))
+}
+
+fn fail_above(size: usize) -> Alloc {
+    TrackingAllocator::new_in(FailingAllocator::fail_above(size))
+}
+
+#[test]
+fn test_failing_allocator() {
+    let alloc = FailingAllocator::<std::alloc::Global>::fail_nth(1);
+    let layout = Layout::new::<u64>();
+
+    let first = alloc.allocate(layout).unwrap();
+    assert!(alloc.allocate(layout).is_err());
+    let third = alloc.allocate(layout).unwrap();
+    assert_eq!(alloc.attempts(), 3);
+
+    unsafe {
+        alloc.deallocate(first.cast(), layout);
+        alloc.deallocate(third.cast(), layout);
+    }
+
+    let alloc = FailingAllocator::<std::alloc::Global>::fail_above(8);
+    assert!(alloc.allocate(Layout::new::<u128>()).is_err());
+
+    let block = alloc.allocate(layout).unwrap();
+    unsafe { alloc.deallocate(block.cast(), layout) };
+}
+
+#[test]
+#[should_panic(expected = "deallocated with")]
+fn test_tracking_mismatched_layout() {
+    let alloc = TrackingAllocator::<std::alloc::Global>::new();
+    let block = alloc.allocate(Layout::new::<u64>()).unwrap();
+    unsafe { alloc.deallocate(block.cast(), Layout::new::<u32>()) };
+}
+
+#[test]
+#[should_panic(expected = "double free")]
+fn test_tracking_double_free() {
+    let alloc = TrackingAllocator::<std::alloc::Global>::new();
+    let layout = Layout::new::<u64>();
+    let block = alloc.allocate(layout).unwrap();
+
+    unsafe {
+        alloc.deallocate(block.cast(), layout);
+        alloc.deallocate(block.cast(), layout);
+    }
+}
+
+#[test]
+#[should_panic(expected = "leaked")]
+fn test_tracking_leak() {
+    let alloc = TrackingAllocator::<std::alloc::Global>::new();
+    forget(SmallBox::<_, [usize; 0], _>::try_new_in(1u64, &alloc).unwrap());
+}
+
+#[test]
+fn test_try_new_errors() {
+    let alloc = fail_above(0);
+
+    let boxed = SmallBox::<_, [usize; 1], _>::try_new_in(1usize, &alloc).unwrap();
+    assert!(SmallBox::is_inlined(&boxed));
+
+    assert_eq!(
+        SmallBox::<_, [usize; 1], _>::try_new_in([1usize; 2], &alloc).err(),
+        Some(SmallBoxError::AllocError(Layout::new::<[usize; 2]>()))
+    );
+    assert!(SmallBox::<MaybeUninit<[usize; 2]>, [usize; 1], _>::try_new_uninit_in(&alloc).is_err());
+    assert!(SmallBox::<MaybeUninit<[usize; 2]>, [usize; 1], _>::try_new_zeroed_in(&alloc).is_err());
+    assert!(
+        SmallBox::<[MaybeUninit<usize>], [usize; 1], _>::try_new_uninit_slice_in(2, &alloc)
+            .is_err()
+    );
+    assert!(
+        SmallBox::<[MaybeUninit<usize>], [usize; 1], _>::try_new_zeroed_slice_in(2, &alloc)
+            .is_err()
+    );
+    assert!(SmallBox::<[u8], [usize; 1], _>::try_new_copy_from_in(&[0; 9], &alloc).is_err());
+    assert!(SmallBox::<str, [usize; 1], _>::try_new_clone_from_in("too long", &alloc).is_ok());
+    assert!(
+        SmallBox::<str, [usize; 1], _>::try_new_clone_from_in("much too long", &alloc).is_err()
+    );
+
+    assert_eq!(alloc.live(), 0);
+}
+
+#[test]
+fn test_try_resize_stack_error() {
+    let alloc = fail_above(0);
+
+    let boxed = SmallBox::<_, [usize; 2], _>::try_new_in([1usize, 2], &alloc).unwrap();
+    let boxed = SmallBox::try_resize_stack::<[usize; 1]>(boxed).unwrap_err();
+
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(*boxed, [1, 2]);
+}
+
+#[test]
+fn test_try_into_box_error() {
+    let alloc = fail_above(0);
+
+    let boxed = SmallBox::<_, [usize; 1], _>::try_new_in(1usize, &alloc).unwrap();
+    let boxed = SmallBox::try_into_box(boxed).unwrap_err();
+
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(*boxed, 1);
+}
+
+#[test]
+fn test_try_downcast_errors() {
+    let alloc = fail_above(0);
+
+    let boxed: SmallBox<dyn Any, [usize; 1], _> =
+        SmallBox::coerce(SmallBox::try_new_in(1usize, &alloc).unwrap());
+
+    let boxed = boxed.try_downcast_into_box::<usize>().unwrap_err();
+    assert!(boxed.is::<usize>());
+    let boxed = boxed.try_downcast_resize::<usize, [u8; 0]>().unwrap_err();
+    assert!(boxed.is::<usize>());
+
+    let boxed = boxed.try_downcast_into_box::<u8>().unwrap_err();
+    let boxed = boxed.try_downcast_resize::<u8, [u8; 0]>().unwrap_err();
+
+    let boxed = boxed.try_downcast_resize::<usize, [usize; 2]>().unwrap();
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(*boxed, 1);
+}
+
+#[test]
+fn test_try_truncate_grow_errors() {
+    let alloc = fail_nth(1);
+    let strings = ["a", "b", "c", "d", "e", "f"].map(String::from);
+
+    let mut boxed =
+        SmallBox::<[String], [usize; 3], _>::try_new_clone_from_in(&strings, &alloc).unwrap();
+    assert!(!SmallBox::is_inlined(&boxed));
+
+    // the tail is dropped in place, and a refused shrink moves the rest to a new
+    // block instead
+    SmallBox::try_truncate(&mut boxed, 4).unwrap();
+    assert_eq!(alloc.live(), 1);
+    assert_eq!(&*boxed, &strings[..4]);
+
+    let alloc = fail_nth(1);
+
+    let mut boxed =
+        SmallBox::<[u32], [u32; 2], _>::try_new_copy_from_in(&[1, 2, 3], &alloc).unwrap();
+    assert!(SmallBox::try_grow(&mut boxed, 8, 0).is_err());
+    assert_eq!(&*boxed, &[1, 2, 3]);
+
+    SmallBox::try_grow(&mut boxed, 8, 0).unwrap();
+    assert_eq!(&*boxed, &[1, 2, 3, 0, 0, 0, 0, 0]);
+
+    let alloc = fail_above(0);
+
+    let mut boxed = SmallBox::<[u32], [u32; 2], _>::try_new_copy_from_in(&[1, 2], &alloc).unwrap();
+    assert!(SmallBox::try_grow(&mut boxed, 3, 0).is_err());
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2]);
+}
+
+#[test]
+fn test_vec_errors() {
+    let alloc = fail_nth(1);
+
+    let mut vec = SmallBoxVec::<u32, [u32; 2], _>::try_with_capacity_in(8, &alloc).unwrap();
+    vec.try_extend_from_slice(&[1, 2, 3]).unwrap();
+
+    assert_eq!(
+        vec.try_reserve(100),
+        Err(SmallBoxError::AllocError(
+            Layout::array::<u32>(103).unwrap()
+        ))
+    );
+    assert_eq!(vec.capacity(), 8);
+    assert_eq!(&*vec, &[1, 2, 3]);
+
+    let alloc = fail_nth(1);
+
+    let mut vec = SmallBoxVec::<u32, [u32; 2], _>::try_with_capacity_in(8, &alloc).unwrap();
+    vec.try_extend_from_slice(&[1, 2, 3]).unwrap();
+
+    // freezing shrinks the block to fit first
+    let vec = vec.try_into_boxed_slice().unwrap_err();
+    assert_eq!(&*vec, &[1, 2, 3]);
+
+    let alloc = fail_above(0);
+
+    let mut string = SmallString::<[u8; 4], _>::new_in(&alloc);
+    string.try_push_str("abc").unwrap();
+    assert!(string.try_push_str("de").is_err());
+    assert_eq!(string, "abc");
+}
+
+#[test]
+fn test_dyn_vec_errors() {
+    let alloc = fail_nth(1);
+
+    let mut vec = DynVec::<dyn std::fmt::Debug, _>::new_in(&alloc);
+    vec.try_push(1u8).unwrap();
+
+    // the entry table has room for a few values, but the value region does not
+    assert!(vec.try_push([0u64; 32]).is_err());
+    assert_eq!(vec.len(), 1);
+
+    let alloc = fail_above(0);
+    let mut vec = DynVec::<dyn std::fmt::Debug, _>::new_in(&alloc);
+    assert!(vec.try_push(1u8).is_err());
+    assert!(vec.is_empty());
+
+    let alloc = fail_nth(1);
+    let mut vec = DynVec::<dyn std::fmt::Debug, _>::new_in(&alloc);
+    vec.try_push([1u64; 4]).unwrap();
+
+    assert!(vec.try_pop::<[usize; 1]>().is_err());
+    assert_eq!(vec.len(), 1);
+    assert_eq!(format!("{:?}", vec), "[[1, 1, 1, 1]]");
+}
+
+#[test]
+fn test_clone_from_reuses_storage() {
+    let alloc = TrackingAllocator::<std::alloc::Global>::new();
+    let strings = ["first", "second", "third", "fourth", "fifth"].map(String::from);
+    let slice = |len: usize| {
+        SmallBox::<[String], [usize; 3], _>::try_new_clone_from_in(&strings[..len], &alloc).unwrap()
+    };
+
+    let mut boxed = slice(2);
+    let first = boxed[0].as_ptr();
+
+    // growing onto the heap clones the shared prefix in place
+    boxed.clone_from(&slice(5));
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &strings);
+    assert_eq!(boxed[0].as_ptr(), first);
+
+    // shrinking drops the tail and shrinks the block
+    boxed.clone_from(&slice(2));
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &strings[..2]);
+    assert_eq!(boxed[0].as_ptr(), first);
+    assert_eq!(alloc.live(), 1);
+
+    boxed.clone_from(&slice(1));
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &strings[..1]);
+    assert_eq!(boxed[0].as_ptr(), first);
+    assert_eq!(alloc.live(), 0);
+
+    let string =
+        |value: &str| SmallBox::<str, [u8; 4], _>::try_new_copy_from_in(value, &alloc).unwrap();
+
+    let mut boxed = string("abc");
+    boxed.clone_from(&string("spilled"));
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, "spilled");
+
+    boxed.clone_from(&string("a bit longer"));
+    assert_eq!(&*boxed, "a bit longer");
+    assert_eq!(alloc.live(), 1);
+
+    boxed.clone_from(&string("ab"));
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, "ab");
+    assert_eq!(alloc.live(), 0);
+}
+
+#[test]
+fn test_try_clone_from_slice_errors() {
+    let alloc = fail_nth(1);
+    let strings = ["a", "b", "c", "d", "e"].map(String::from);
+
+    let mut boxed =
+        SmallBox::<[String], [usize; 3], _>::try_new_clone_from_in(&strings[..4], &alloc).unwrap();
+
+    // growing the block fails, the length is kept
+    assert!(SmallBox::try_clone_from_slice(&mut boxed, &strings).is_err());
+    assert_eq!(&*boxed, &strings[..4]);
+
+    SmallBox::try_clone_from_slice(&mut boxed, &strings).unwrap();
+    assert_eq!(&*boxed, &strings);
+
+    let alloc = fail_nth(1);
+
+    let mut boxed =
+        SmallBox::<[String], [usize; 3], _>::try_new_clone_from_in(&strings, &alloc).unwrap();
+
+    // shrinking does not need an allocation unless the allocator refuses to shrink
+    SmallBox::try_clone_from_slice(&mut boxed, &strings[1..5]).unwrap();
+    assert_eq!(&*boxed, &strings[1..5]);
+
+    let alloc = fail_nth(1);
+
+    let mut boxed =
+        SmallBox::<[u32], [u32; 2], _>::try_new_copy_from_in(&[1, 2, 3, 4], &alloc).unwrap();
+    SmallBox::try_clone_from_slice(&mut boxed, &[5, 6]).unwrap();
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(alloc.live(), 0);
+    assert_eq!(&*boxed, &[5, 6]);
+}
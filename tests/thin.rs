@@ -0,0 +1,162 @@
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{alloc::Global, any::Any, cell::Cell, fmt::Debug, mem::size_of, rc::Rc};
+
+use assert_no_alloc::*;
+use smolbox::{SmallBox, ThinSmallBox};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_sizes() {
+    let ptr = size_of::<usize>();
+
+    // the metadata lives next to the value, so without inline space only the pointer is left
+    assert_eq!(size_of::<ThinSmallBox<u8, [usize; 0], Global>>(), ptr);
+    assert_eq!(size_of::<ThinSmallBox<[u8], [usize; 0], Global>>(), ptr);
+    assert_eq!(size_of::<ThinSmallBox<dyn Any, [usize; 0], Global>>(), ptr);
+    assert_eq!(
+        size_of::<ThinSmallBox<dyn Any, [usize; 2], Global>>(),
+        3 * ptr
+    );
+
+    assert_eq!(
+        size_of::<Option<ThinSmallBox<[u8], [usize; 0], Global>>>(),
+        ptr
+    );
+    assert_eq!(
+        size_of::<Option<ThinSmallBox<dyn Any, [usize; 2], Global>>>(),
+        3 * ptr
+    );
+}
+
+#[test]
+fn test_inlined_dyn() {
+    assert_no_alloc(|| {
+        let mut boxed: ThinSmallBox<dyn Any, [usize; 2], Global> =
+            ThinSmallBox::try_new_unsize(1usize).unwrap();
+
+        assert!(ThinSmallBox::is_inlined(&boxed));
+        assert_eq!(boxed.downcast_ref::<usize>(), Some(&1));
+
+        *boxed.downcast_mut::<usize>().unwrap() = 2;
+        assert_eq!(boxed.downcast_ref::<usize>(), Some(&2));
+    });
+
+    // the metadata takes a word of the inline space
+    let boxed: ThinSmallBox<dyn Any, [usize; 2], Global> =
+        ThinSmallBox::try_new_unsize([1usize, 2]).unwrap();
+
+    assert!(!ThinSmallBox::is_inlined(&boxed));
+    assert_eq!(boxed.downcast_ref::<[usize; 2]>(), Some(&[1, 2]));
+}
+
+#[test]
+fn test_heap_slice() {
+    let boxed: ThinSmallBox<[u32], [usize; 0], Global> =
+        ThinSmallBox::try_new_unsize([1u32, 2, 3]).unwrap();
+
+    assert!(!ThinSmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2, 3]);
+
+    let empty: ThinSmallBox<[u32], [usize; 0], Global> = ThinSmallBox::try_new_unsize([]).unwrap();
+    assert!(empty.is_empty());
+}
+
+#[test]
+fn test_sized() {
+    let boxed = ThinSmallBox::<_, [usize; 1], Global>::try_new(3usize).unwrap();
+    assert!(ThinSmallBox::is_inlined(&boxed));
+    assert_eq!(ThinSmallBox::into_inner(boxed), 3);
+
+    let boxed = ThinSmallBox::<_, [usize; 1], Global>::try_new(String::from("spilled")).unwrap();
+    assert!(!ThinSmallBox::is_inlined(&boxed));
+    assert_eq!(ThinSmallBox::into_inner(boxed), "spilled");
+}
+
+#[test]
+fn test_overaligned() {
+    #[derive(Debug)]
+    #[allow(dead_code)]
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    let boxed: ThinSmallBox<dyn Debug, [u8; 256], Global> =
+        ThinSmallBox::try_new_unsize(Aligned(7)).unwrap();
+
+    assert!(!ThinSmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed as *const dyn Debug as *const u8 as usize % 64, 0);
+    assert_eq!(format!("{:?}", boxed), "Aligned(7)");
+}
+
+#[test]
+fn test_drop() {
+    struct Counted(Rc<Cell<usize>>);
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let counter = Rc::new(Cell::new(0));
+
+    let inlined: ThinSmallBox<dyn Any, [usize; 2], Global> =
+        ThinSmallBox::try_new_unsize(Counted(counter.clone())).unwrap();
+    let heap: ThinSmallBox<dyn Any, [usize; 0], Global> =
+        ThinSmallBox::try_new_unsize(Counted(counter.clone())).unwrap();
+
+    assert!(ThinSmallBox::is_inlined(&inlined));
+    assert!(!ThinSmallBox::is_inlined(&heap));
+
+    drop(inlined);
+    drop(heap);
+    assert_eq!(counter.get(), 2);
+
+    let counted = ThinSmallBox::<_, [usize; 0], Global>::try_new(Counted(counter.clone())).unwrap();
+    drop(ThinSmallBox::into_inner(counted));
+    assert_eq!(counter.get(), 3);
+}
+
+#[test]
+fn test_small_conversions() {
+    let small = SmallBox::<[u8], [usize; 2], Global>::try_copy_from(&[1, 2, 3]).unwrap();
+    assert!(SmallBox::is_inlined(&small));
+
+    let thin = ThinSmallBox::try_from_small(small).unwrap();
+    assert!(ThinSmallBox::is_inlined(&thin));
+    assert_eq!(&*thin, &[1, 2, 3]);
+
+    let small = ThinSmallBox::try_into_small(thin).unwrap();
+    assert!(SmallBox::is_inlined(&small));
+    assert_eq!(&*small, &[1, 2, 3]);
+
+    // fits into the space, but not next to the metadata
+    let small = SmallBox::<[u8], [usize; 2], Global>::try_copy_from(&[7; 16]).unwrap();
+    assert!(SmallBox::is_inlined(&small));
+
+    let thin = ThinSmallBox::try_from_small(small).unwrap();
+    assert!(!ThinSmallBox::is_inlined(&thin));
+    assert_eq!(&*thin, &[7; 16]);
+
+    let small = ThinSmallBox::try_into_small(thin).unwrap();
+    assert!(SmallBox::is_inlined(&small));
+    assert_eq!(&*small, &[7; 16]);
+}
+
+#[test]
+#[cfg(feature = "alloc")]
+fn test_from_into() {
+    let small: SmallBox<dyn Debug, [usize; 0], Global> =
+        SmallBox::coerce(SmallBox::new(String::from("heap")));
+    let thin = ThinSmallBox::from(small);
+    assert_eq!(format!("{:?}", thin), "\"heap\"");
+
+    let small: SmallBox<dyn Debug, [usize; 0], Global> = SmallBox::from(thin);
+    assert!(!SmallBox::is_inlined(&small));
+    assert_eq!(format!("{:?}", small), "\"heap\"");
+}
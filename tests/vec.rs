@@ -0,0 +1,242 @@
+#![cfg(feature = "alloc")]
+#![feature(allocator_api)]
+
+extern crate smolbox;
+
+use std::{alloc::Global, cell::Cell, rc::Rc};
+
+use assert_no_alloc::*;
+use smolbox::{SmallBox, SmallBoxError, SmallBoxVec};
+
+#[cfg(not(miri))]
+#[global_allocator]
+static A: AllocDisabler = AllocDisabler;
+
+#[test]
+fn test_inlined_push_pop() {
+    assert_no_alloc(|| {
+        let mut vec = SmallBoxVec::<u32, [u32; 4], Global>::new();
+        assert!(vec.is_inlined());
+        assert_eq!(vec.capacity(), 4);
+
+        for i in 0..4 {
+            vec.push(i);
+        }
+
+        assert!(vec.is_inlined());
+        assert_eq!(&*vec, &[0, 1, 2, 3]);
+
+        assert_eq!(vec.pop(), Some(3));
+        assert_eq!(vec.pop(), Some(2));
+        assert_eq!(&*vec, &[0, 1]);
+    });
+}
+
+#[test]
+fn test_spill() {
+    let mut vec = SmallBoxVec::<u32, [u32; 2], Global>::new();
+    vec.extend([1, 2]);
+    assert!(vec.is_inlined());
+
+    vec.push(3);
+    assert!(!vec.is_inlined());
+    assert!(vec.capacity() >= 3);
+    assert_eq!(&*vec, &[1, 2, 3]);
+
+    vec.extend(4..100);
+    assert_eq!(vec.len(), 99);
+    assert!(vec.iter().copied().eq(1..100));
+}
+
+#[test]
+fn test_insert_remove() {
+    let mut vec = SmallBoxVec::<u32, [u32; 4], Global>::new();
+    vec.insert(0, 2);
+    vec.insert(0, 0);
+    vec.insert(1, 1);
+    vec.insert(3, 3);
+    assert!(vec.is_inlined());
+    assert_eq!(&*vec, &[0, 1, 2, 3]);
+
+    vec.insert(2, 10);
+    assert!(!vec.is_inlined());
+    assert_eq!(&*vec, &[0, 1, 10, 2, 3]);
+
+    assert_eq!(vec.remove(2), 10);
+    assert_eq!(vec.remove(0), 0);
+    assert_eq!(vec.remove(2), 3);
+    assert_eq!(&*vec, &[1, 2]);
+}
+
+#[test]
+#[should_panic]
+fn test_remove_out_of_bounds() {
+    let mut vec = SmallBoxVec::<u32, [u32; 4], Global>::new();
+    vec.push(1);
+    vec.remove(1);
+}
+
+#[test]
+fn test_reserve_shrink() {
+    let mut vec = SmallBoxVec::<u32, [u32; 4], Global>::new();
+    vec.extend([1, 2, 3]);
+
+    vec.reserve(10);
+    assert!(!vec.is_inlined());
+    assert!(vec.capacity() >= 13);
+
+    vec.shrink_to_fit();
+    assert!(vec.is_inlined());
+    assert_eq!(vec.capacity(), 4);
+    assert_eq!(&*vec, &[1, 2, 3]);
+
+    vec.extend(4..=8);
+    vec.reserve_exact(100);
+    assert!(vec.capacity() >= 108);
+
+    vec.shrink_to_fit();
+    assert!(!vec.is_inlined());
+    assert_eq!(vec.capacity(), 8);
+    assert!(vec.iter().copied().eq(1..=8));
+
+    assert_eq!(
+        vec.try_reserve(usize::MAX),
+        Err(SmallBoxError::CapacityOverflow)
+    );
+    assert_eq!(
+        vec.try_reserve_exact(usize::MAX / 2),
+        Err(SmallBoxError::CapacityOverflow)
+    );
+    assert_eq!(vec.capacity(), 8);
+}
+
+#[test]
+fn test_drain() {
+    let mut vec = (0..10).collect::<SmallBoxVec<u32, [u32; 4], Global>>();
+
+    assert!(vec.drain(2..5).eq([2, 3, 4]));
+    assert_eq!(&*vec, &[0, 1, 5, 6, 7, 8, 9]);
+
+    assert!(vec.drain(..2).rev().eq([1, 0]));
+    assert_eq!(&*vec, &[5, 6, 7, 8, 9]);
+
+    // dropping a partially consumed drain still removes the whole range
+    let mut drain = vec.drain(1..=3);
+    assert_eq!(drain.next(), Some(6));
+    drop(drain);
+    assert_eq!(&*vec, &[5, 9]);
+
+    assert!(vec.drain(..).eq([5, 9]));
+    assert!(vec.is_empty());
+}
+
+#[test]
+fn test_drop() {
+    let counter = Rc::new(Cell::new(0));
+
+    struct Counted(Rc<Cell<usize>>);
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.set(self.0.get() + 1);
+        }
+    }
+
+    let mut vec = SmallBoxVec::<Counted, [usize; 2], Global>::new();
+    for _ in 0..8 {
+        vec.push(Counted(counter.clone()));
+    }
+
+    vec.truncate(6);
+    assert_eq!(counter.get(), 2);
+
+    drop(vec.drain(1..3));
+    assert_eq!(counter.get(), 4);
+
+    drop(vec.pop());
+    assert_eq!(counter.get(), 5);
+
+    drop(vec);
+    assert_eq!(counter.get(), 8);
+}
+
+#[test]
+fn test_zst() {
+    let mut vec = SmallBoxVec::<(), [usize; 0], Global>::new();
+    assert_eq!(vec.capacity(), usize::MAX);
+
+    for _ in 0..1000 {
+        vec.push(());
+    }
+
+    assert_eq!(vec.len(), 1000);
+    vec.shrink_to_fit();
+
+    let boxed = vec.into_boxed_slice();
+    assert_eq!(boxed.len(), 1000);
+    assert_eq!(boxed.into_vec().len(), 1000);
+}
+
+#[test]
+fn test_box_conversions_inlined() {
+    assert_no_alloc(|| {
+        let boxed = SmallBox::<[u32], [u32; 4], Global>::try_copy_from(&[1, 2]).unwrap();
+        assert!(SmallBox::is_inlined(&boxed));
+
+        let mut vec = boxed.into_vec();
+        assert!(vec.is_inlined());
+        assert_eq!(vec.capacity(), 4);
+
+        vec.push(3);
+
+        let boxed = vec.into_boxed_slice();
+        assert!(SmallBox::is_inlined(&boxed));
+        assert_eq!(&*boxed, &[1, 2, 3]);
+    });
+}
+
+#[test]
+fn test_box_conversions_heap() {
+    let boxed = SmallBox::<[u32], [u32; 2], Global>::copy_from(&[1, 2, 3]);
+    assert!(!SmallBox::is_inlined(&boxed));
+
+    let mut vec = boxed.into_vec();
+    assert!(!vec.is_inlined());
+    assert_eq!(vec.capacity(), 3);
+
+    vec.push(4);
+
+    let boxed: SmallBox<[u32], [u32; 2], Global> = SmallBox::from(vec);
+    assert!(!SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2, 3, 4]);
+
+    // freezing a spilled vector that fits again moves it back inline
+    let mut vec = boxed.into_vec();
+    vec.truncate(2);
+
+    let boxed = vec.into_boxed_slice();
+    assert!(SmallBox::is_inlined(&boxed));
+    assert_eq!(&*boxed, &[1, 2]);
+}
+
+#[test]
+fn test_overaligned() {
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    #[repr(align(64))]
+    struct Aligned(u8);
+
+    let mut vec = SmallBoxVec::<Aligned, [u8; 256], Global>::new();
+    assert!(!vec.is_inlined());
+    assert_eq!(vec.capacity(), 0);
+
+    let boxed = SmallBoxVec::<Aligned, [u8; 256], Global>::new().into_boxed_slice();
+    assert!(boxed.is_empty());
+    assert!(boxed.into_vec().is_empty());
+
+    vec.push(Aligned(1));
+    vec.push(Aligned(2));
+    assert_eq!(vec.as_ptr() as usize % 64, 0);
+
+    vec.clear();
+    vec.shrink_to_fit();
+    assert_eq!(vec.capacity(), 0);
+}